@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+drop index bookmarks_url_key;
+alter table bookmarks drop column url_key;
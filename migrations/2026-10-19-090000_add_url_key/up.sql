@@ -0,0 +1,3 @@
+alter table bookmarks add column url_key text not null default '';
+
+create index bookmarks_url_key on bookmarks(url_key);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+
+alter table bookmarks drop column deleted_at;
@@ -0,0 +1 @@
+alter table bookmarks add column deleted_at bigint;
@@ -0,0 +1,13 @@
+create table revisions (
+  id integer primary key autoincrement not null,
+  bookmark_id integer not null,
+  rev integer not null,
+  action text not null,
+  url text not null,
+  description text not null,
+  tags text not null,
+  created_at bigint not null,
+  foreign key(bookmark_id) references bookmarks(id)
+);
+
+create index revisions_bookmark_id on revisions(bookmark_id);
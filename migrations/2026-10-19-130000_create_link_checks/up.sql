@@ -0,0 +1,9 @@
+create table link_checks (
+  bookmark_id integer primary key not null,
+  url text not null,
+  status integer,
+  redirect text,
+  error text,
+  checked_at bigint not null,
+  foreign key(bookmark_id) references bookmarks(id)
+);
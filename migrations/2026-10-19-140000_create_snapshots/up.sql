@@ -0,0 +1,11 @@
+create table snapshots (
+  id integer primary key autoincrement not null,
+  bookmark_id integer not null,
+  hash text not null,
+  url text not null,
+  size bigint not null,
+  created_at bigint not null,
+  foreign key(bookmark_id) references bookmarks(id)
+);
+
+create index snapshots_bookmark_id on snapshots(bookmark_id);
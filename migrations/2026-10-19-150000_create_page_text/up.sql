@@ -0,0 +1,5 @@
+-- Readable text of bookmarked pages, the rowid is the bookmark id
+create virtual table page_text using fts5(
+  text,
+  tokenize = 'porter unicode61 remove_diacritics 2'
+);
@@ -0,0 +1,8 @@
+create table visits (
+  id integer primary key autoincrement not null,
+  bookmark_id integer not null,
+  visited_at bigint not null,
+  foreign key(bookmark_id) references bookmarks(id)
+);
+
+create index visits_bookmark_id on visits(bookmark_id);
@@ -0,0 +1,392 @@
+// Copyright 2024 João Freitas
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of
+// the GNU General Public License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS FOR
+// A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Offline copies of bookmarked pages.
+//!
+//! A snapshot is a single html file with its images, stylesheets and scripts
+//! inlined as data urls. Snapshots are stored in files named after their sha1
+//! hash, so identical copies are only kept once.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use reqwest::blocking::Client;
+use reqwest::header::CONTENT_TYPE;
+use scraper::{Html, Node, Selector, StrTendril};
+use sha1::{Digest, Sha1};
+use url::Url;
+
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+/// Assets larger than this are linked instead of inlined
+const MAX_ASSET_SIZE: usize = 10 * 1024 * 1024;
+/// Pages whose html is larger than this aren't archived
+const MAX_PAGE_SIZE: usize = 16 * 1024 * 1024;
+/// Bytes of a page read at most to index its text
+const MAX_TEXT_PAGE_SIZE: usize = 4 * 1024 * 1024;
+
+/// Elements whose attribute is replaced by the content it points to
+const ASSETS: &[(&str, &str)] = &[
+    ("img[src]", "src"),
+    ("script[src]", "src"),
+    ("link[rel~=stylesheet][href]", "href"),
+    ("link[rel~=icon][href]", "href"),
+    ("input[type=image][src]", "src"),
+];
+
+/// Elements whose attribute is made absolute so it still works offline
+const LINKS: &[(&str, &str)] = &[
+    ("a[href]", "href"),
+    ("form[action]", "action"),
+    ("iframe[src]", "src"),
+    ("video[src]", "src"),
+    ("audio[src]", "src"),
+    ("source[src]", "src"),
+];
+
+fn client() -> Result<Client, String> {
+    Client::builder()
+        .timeout(TIMEOUT)
+        .build()
+        .map_err(|err| format!("{}", err))
+}
+
+/// Download `url` as a data url, none if it can't be fetched or is too large.
+fn data_url(client: &Client, url: &Url) -> Option<String> {
+    let response = client
+        .get(url.as_str())
+        .send()
+        .ok()?
+        .error_for_status()
+        .ok()?;
+    let mime = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|mime| mime.to_str().ok())
+        .unwrap_or("application/octet-stream")
+        .to_string();
+    let mut bytes = vec![];
+    response
+        .take(MAX_ASSET_SIZE as u64 + 1)
+        .read_to_end(&mut bytes)
+        .ok()?;
+
+    if bytes.len() > MAX_ASSET_SIZE {
+        None
+    } else {
+        Some(format!("data:{};base64,{}", mime, STANDARD.encode(bytes)))
+    }
+}
+
+/// Set attribute `name` of the elements matched by `css` to the value
+/// returned by `rewrite`, if any.
+fn rewrite_attrs<F>(document: &mut Html, css: &str, name: &str, rewrite: F)
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let selector = Selector::parse(css).expect("Invariant: selectors are valid css");
+    let targets: Vec<_> = document
+        .select(&selector)
+        .filter_map(|element| Some((element.id(), rewrite(element.value().attr(name)?)?)))
+        .collect();
+
+    for (id, value) in targets {
+        if let Some(mut node) = document.tree.get_mut(id) {
+            if let Node::Element(element) = node.value() {
+                element.attrs.retain(|attr, _| &*attr.local != "srcset");
+                for (attr, old) in element.attrs.iter_mut() {
+                    if &*attr.local == name {
+                        *old = StrTendril::from(value.as_str());
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Inline the assets of `html`, a page downloaded from `base`, into a single
+/// html document.
+pub fn inline(html: &str, base: &Url, fetch: impl Fn(&Url) -> Option<String>) -> String {
+    let mut document = Html::parse_document(html);
+    let resolve = |href: &str| base.join(href).ok();
+
+    for (css, name) in ASSETS {
+        rewrite_attrs(&mut document, css, name, |href| {
+            if href.starts_with("data:") {
+                return None;
+            }
+            resolve(href).and_then(|url| fetch(&url))
+        });
+    }
+    for (css, name) in LINKS {
+        rewrite_attrs(&mut document, css, name, |href| {
+            if href.starts_with('#') {
+                return None;
+            }
+            resolve(href).map(|url| url.to_string())
+        });
+    }
+
+    document.html()
+}
+
+/// Download at most `max` bytes of the html of the page at `url`, returning
+/// them with the url it was served from after redirects and whether the page
+/// was longer.
+fn fetch_page(client: &Client, url: &str, max: usize) -> Result<(Url, String, bool), String> {
+    let response = client
+        .get(url)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| format!("Failed to fetch {}: {}", url, err))?;
+
+    let base = response.url().clone();
+    let mut html = vec![];
+    response
+        .take(max as u64 + 1)
+        .read_to_end(&mut html)
+        .map_err(|err| format!("Failed to read {}: {}", url, err))?;
+    let truncated = html.len() > max;
+    html.truncate(max);
+
+    Ok((base, String::from_utf8_lossy(&html).to_string(), truncated))
+}
+
+/// Download the page at `url` and its assets as a single html document.
+pub fn snapshot_page(url: &str) -> Result<String, String> {
+    let client = client()?;
+    let (base, html, truncated) = fetch_page(&client, url, MAX_PAGE_SIZE)?;
+    if truncated {
+        return Err(format!(
+            "{} is larger than {} MiB",
+            url,
+            MAX_PAGE_SIZE >> 20
+        ));
+    }
+    Ok(inline(&html, &base, |asset| data_url(&client, asset)))
+}
+
+/// Download the start of the page at `url` and extract its readable text.
+pub fn fetch_text(url: &str) -> Result<String, String> {
+    let (_, html, _) = fetch_page(&client()?, url, MAX_TEXT_PAGE_SIZE)?;
+    Ok(page_text(&html))
+}
+
+/// Readable text of `html`, leaving out scripts, styles and other markup.
+pub fn page_text(html: &str) -> String {
+    const HIDDEN: &[&str] = &["script", "style", "noscript", "template", "svg", "head"];
+
+    let document = Html::parse_document(html);
+    let title = document
+        .select(&Selector::parse("title").expect("Invariant: selectors are valid css"))
+        .flat_map(|title| title.text())
+        .collect::<String>();
+    let body = document.tree.root().descendants().filter_map(|node| {
+        let text = node.value().as_text()?;
+        let hidden = node.ancestors().any(|ancestor| match ancestor.value() {
+            Node::Element(element) => HIDDEN.contains(&element.name()),
+            _ => false,
+        });
+        (!hidden).then_some(&**text)
+    });
+
+    std::iter::once(title.as_str())
+        .chain(body)
+        .flat_map(|text| text.split_whitespace())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Hex encoded sha1 of `content`
+pub fn hash(content: &str) -> String {
+    Sha1::digest(content.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Directory of snapshot files addressed by their hash
+pub struct Store {
+    dir: PathBuf,
+}
+
+impl Store {
+    /// Store kept next to the database at `db`
+    pub fn for_db(db: &str) -> Store {
+        Store {
+            dir: PathBuf::from(format!("{}.archive", db)),
+        }
+    }
+
+    fn path(&self, hash: &str) -> Result<PathBuf, String> {
+        if hash.len() != 40 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("Invalid snapshot hash {}", hash));
+        }
+        Ok(self
+            .dir
+            .join(&hash[..2])
+            .join(format!("{}.html", &hash[2..])))
+    }
+
+    /// Save `content` unless an identical copy exists, returning its hash.
+    pub fn put(&self, content: &str) -> Result<String, String> {
+        let hash = hash(content);
+        let path = self.path(&hash)?;
+        if path.exists() {
+            return Ok(hash);
+        }
+
+        let dir = path.parent().unwrap_or(Path::new("."));
+        fs::create_dir_all(dir).map_err(|err| format!("{}", err))?;
+        let mut file = tempfile::NamedTempFile::new_in(dir).map_err(|err| format!("{}", err))?;
+        file.write_all(content.as_bytes())
+            .map_err(|err| format!("{}", err))?;
+        file.persist(&path).map_err(|err| format!("{}", err))?;
+
+        Ok(hash)
+    }
+
+    pub fn get(&self, hash: &str) -> Result<String, String> {
+        fs::read_to_string(self.path(hash)?).map_err(|_| "Snapshot not found".to_string())
+    }
+
+    pub fn remove(&self, hash: &str) -> Result<(), String> {
+        match fs::remove_file(self.path(hash)?) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(format!("{}", err)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Serve `body` as html to every request.
+    fn serve(body: String) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = [0; 1024];
+                let _ = stream.read(&mut request).unwrap();
+                // The client may hang up before reading large bodies
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+            }
+        });
+
+        format!("http://{}/page.html", addr)
+    }
+
+    #[test]
+    fn assets_are_inlined() {
+        let html = r##"<html><head>
+            <link rel="stylesheet" href="/style.css">
+            <script src="app.js"></script>
+            </head><body>
+            <img src="img/logo.png" srcset="img/logo@2x.png 2x">
+            <img src="data:image/gif;base64,R0lGOD">
+            <img src="https://cdn.example.com/missing.png">
+            <a href="../about">About</a> <a href="#top">Top</a>
+            </body></html>"##;
+        let base = Url::parse("https://example.com/docs/").unwrap();
+
+        let snapshot = inline(html, &base, |url| match url.path() {
+            "/style.css" => Some("data:text/css;base64,Ym9keXt9".to_string()),
+            "/docs/app.js" => Some("data:text/javascript;base64,MQ==".to_string()),
+            "/docs/img/logo.png" => Some("data:image/png;base64,iVBO".to_string()),
+            _ => None,
+        });
+
+        assert!(snapshot.contains(r#"href="data:text/css;base64,Ym9keXt9""#));
+        assert!(snapshot.contains(r#"src="data:text/javascript;base64,MQ==""#));
+        assert!(snapshot.contains(r#"src="data:image/png;base64,iVBO""#));
+        assert!(snapshot.contains(r#"src="data:image/gif;base64,R0lGOD""#));
+        assert!(snapshot.contains(r#"src="https://cdn.example.com/missing.png""#));
+        assert!(snapshot.contains(r#"href="https://example.com/about""#));
+        assert!(snapshot.contains(r##"href="#top""##));
+        assert!(!snapshot.contains("srcset"));
+    }
+
+    #[test]
+    fn large_downloads_are_rejected() {
+        let client = client().unwrap();
+        let small = Url::parse(&serve("body {}".to_string())).unwrap();
+        let large = Url::parse(&serve("x".repeat(MAX_ASSET_SIZE + 1))).unwrap();
+
+        assert_eq!(
+            data_url(&client, &small),
+            Some("data:text/html;base64,Ym9keSB7fQ==".to_string())
+        );
+        assert_eq!(data_url(&client, &large), None);
+
+        let page = serve(format!("<p>{}</p>", "x".repeat(MAX_PAGE_SIZE)));
+        assert!(snapshot_page(&page).unwrap_err().contains("larger than"));
+    }
+
+    #[test]
+    fn only_the_start_of_pages_is_indexed() {
+        let page = serve(format!(
+            "<p>start {}</p><p>end</p>",
+            "x".repeat(MAX_TEXT_PAGE_SIZE)
+        ));
+        let text = fetch_text(&page).unwrap();
+
+        assert!(text.starts_with("start x"));
+        assert!(!text.ends_with("end"));
+    }
+
+    #[test]
+    fn text_is_extracted() {
+        let html = r#"<html><head><title>Rust  Book</title>
+            <style>body { color: red }</style></head>
+            <body><h1>Ownership</h1><script>var hidden = 1;</script>
+            <p>Each value has <em>an owner</em>.</p><noscript>Enable js</noscript>
+            </body></html>"#;
+
+        assert_eq!(
+            page_text(html),
+            "Rust Book Ownership Each value has an owner ."
+        );
+    }
+
+    #[test]
+    fn store_is_content_addressed() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store {
+            dir: dir.path().to_path_buf(),
+        };
+
+        let hash = store.put("<p>page</p>").unwrap();
+        assert_eq!(hash, "726098488acb8eeeb38f80de478fa602d9feebc3");
+        assert_eq!(store.put("<p>page</p>").unwrap(), hash);
+        assert_eq!(store.get(&hash).unwrap(), "<p>page</p>");
+
+        store.remove(&hash).unwrap();
+        assert!(store.get(&hash).is_err());
+        assert!(store.get("../../etc/passwd").is_err());
+    }
+}
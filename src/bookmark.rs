@@ -11,12 +11,14 @@
 // You should have received a copy of the GNU General Public License along with this
 // program. If not, see <https://www.gnu.org/licenses/>.
 
-use itertools::intersperse;
+use itertools::{intersperse, Itertools};
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
+use chrono::{DateTime, Local};
 use colored::Colorize;
 
+use crate::metadata::PageMetadata;
 use crate::models::{Bookmarks, Tags};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -25,6 +27,110 @@ pub struct Bookmark {
     pub tags: Vec<Tags>,
 }
 
+/// Input for creating a new bookmark
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct NewBookmark {
+    pub url: String,
+    pub description: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub metadata: Option<PageMetadata>,
+}
+
+/// State of a bookmark after one of its changes
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Revision {
+    pub bookmark_id: i32,
+    /// Revision number, counted per bookmark starting at 1
+    pub rev: i32,
+    /// Change that produced this revision: add, update, revert, delete or restore
+    pub action: String,
+    pub url: String,
+    pub description: String,
+    pub tags: Vec<String>,
+    pub created_at: i64,
+}
+
+/// Outcome of requesting the url of a bookmark
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LinkCheck {
+    pub bookmark_id: i32,
+    /// Url that was requested
+    pub url: String,
+    /// Http status of the response, none if the request failed
+    pub status: Option<i32>,
+    /// Target of a redirect response
+    pub redirect: Option<String>,
+    /// Why the request failed
+    pub error: Option<String>,
+    pub checked_at: i64,
+}
+
+impl LinkCheck {
+    /// Whether the request failed or the server answered with an error status
+    pub fn is_broken(&self) -> bool {
+        self.error.is_some() || !matches!(self.status, Some(status) if status < 400)
+    }
+
+    /// New url of a page that moved permanently
+    pub fn moved_to(&self) -> Option<&str> {
+        match self.status {
+            Some(301) | Some(308) => self.redirect.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// Tag with the number of bookmarks, not in the trash, that have it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: i64,
+}
+
+/// How often and how recently a bookmark was opened
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Visits {
+    pub bookmark_id: i32,
+    pub count: i64,
+    pub last_visit: i64,
+    /// Score combining the number and age of visits, higher is more relevant
+    pub frecency: i64,
+}
+
+/// Bookmark found by a search
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SearchResult {
+    pub bookmark: Bookmark,
+    /// Page text around the match, with matching words between `<mark>` and `</mark>`
+    pub snippet: Option<String>,
+}
+
+/// Offline copy of a bookmarked page
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    pub bookmark_id: i32,
+    /// Sha1 of the stored html, identifying it in the archive
+    pub hash: String,
+    /// Url the page was downloaded from
+    pub url: String,
+    /// Size of the stored html in bytes
+    pub size: i64,
+    pub created_at: i64,
+}
+
+/// Format a unix timestamp in the local timezone
+pub fn format_timestamp(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .map(|time| {
+            time.with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
 impl Bookmark {
     pub fn new(bookmark: &Bookmarks, tags: &[Tags]) -> Bookmark {
         Bookmark {
@@ -32,6 +138,33 @@ impl Bookmark {
             tags: tags.to_vec(),
         }
     }
+
+    /// Names of the tags of the bookmark
+    pub fn tag_names(&self) -> Vec<String> {
+        self.tags.iter().map(|tag| tag.tag.clone()).collect()
+    }
+
+    /// Page metadata stored with the bookmark
+    pub fn metadata(&self) -> Option<PageMetadata> {
+        serde_json::from_str(self.bookmark.metadata.as_ref()?).ok()
+    }
+
+    /// Whether every word of `query` appears, ignoring case, in the
+    /// description, url or tags.
+    pub fn matches(&self, query: &str) -> bool {
+        let haystack = format!(
+            "{} {} {}",
+            self.bookmark.description,
+            self.bookmark.url,
+            self.tags.iter().map(|tag| tag.tag.as_str()).join(" ")
+        )
+        .to_lowercase();
+
+        query
+            .to_lowercase()
+            .split_whitespace()
+            .all(|word| haystack.contains(word))
+    }
 }
 
 impl fmt::Display for Bookmark {
@@ -61,3 +194,84 @@ impl fmt::Display for Bookmark {
         }
     }
 }
+
+impl fmt::Display for Revision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {}\n  {}\n  {} {}",
+            format!("r{}.", self.rev).cyan(),
+            self.action.magenta(),
+            format_timestamp(self.created_at),
+            self.description.green(),
+            ">".red(),
+            self.url.yellow()
+        )?;
+
+        if !self.tags.is_empty() {
+            write!(f, "\n{} {}", "#".red(), self.tags.join(",").blue())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl fmt::Display for LinkCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let outcome = match (&self.error, self.status) {
+            (Some(error), _) => error.red(),
+            (None, Some(status)) if self.is_broken() => status.to_string().red(),
+            (None, Some(status)) => status.to_string().green(),
+            (None, None) => "unknown".red(),
+        };
+        write!(
+            f,
+            "{} {} {}\n  {} {}",
+            format!("{}.", self.bookmark_id).cyan(),
+            outcome,
+            format_timestamp(self.checked_at),
+            ">".red(),
+            self.url.yellow()
+        )?;
+
+        match &self.redirect {
+            Some(redirect) => write!(f, "\n  {} {}", "->".red(), redirect.yellow()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} KiB\n  {} {}",
+            format!("{}.", self.bookmark_id).cyan(),
+            format_timestamp(self.created_at),
+            (self.size + 1023) / 1024,
+            self.hash.magenta(),
+            self.url.yellow()
+        )
+    }
+}
+
+impl fmt::Display for SearchResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.bookmark)?;
+
+        match &self.snippet {
+            Some(snippet) => {
+                let highlighted = snippet
+                    .split("<mark>")
+                    .enumerate()
+                    .map(|(i, part)| match part.split_once("</mark>") {
+                        Some((mark, rest)) if i > 0 => format!("{}{}", mark.bold().red(), rest),
+                        _ => part.to_string(),
+                    })
+                    .collect::<String>();
+                write!(f, "\n  {} {}", "~".red(), highlighted)
+            }
+            None => Ok(()),
+        }
+    }
+}
@@ -11,87 +11,48 @@
 // You should have received a copy of the GNU General Public License along with this
 // program. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::bookmark::Bookmark;
+use crate::bookmark::{
+    Bookmark, LinkCheck, NewBookmark, Revision, SearchResult, Snapshot, TagCount, Visits,
+};
 
 pub trait BookmarkProxy {
     fn bookmarks(&self) -> Result<Vec<Bookmark>, String>;
     fn bookmark(&self, id: i32) -> Result<Bookmark, String>;
-    fn add(&self, url: &str, description: &str, tags: Vec<String>) -> Result<(), String>;
+    fn add(&self, bookmark: &NewBookmark) -> Result<Bookmark, String>;
     fn delete(&self, id: i32) -> Result<(), String>;
     fn update_description(&self, id: i32, descritption: &str) -> Result<(), String>;
     fn update_url(&self, id: i32, url: &str) -> Result<(), String>;
     fn update_tags(&self, id: i32, tags: &[String]) -> Result<(), String>;
-}
-
-use itertools::intersperse;
-
-use std::{
-    env::{temp_dir, var},
-    fs::File,
-    io::{Read, Write},
-    process::Command,
-};
-
-pub fn edit_bookmark(proxy: &dyn BookmarkProxy, id: i32, visual: Option<bool>) {
-    let editor = if visual.is_none() || visual == Some(false) {
-        var("EDITOR").unwrap()
-    } else {
-        var("VISUAL").unwrap()
-    };
-
-    let split: Vec<String> = editor.split(' ').map(|x| x.to_string()).collect();
-    let editor = &split[0];
-    let mut args: Vec<String> = split[1..].iter().map(|x| x.to_string()).collect();
-
-    let mut file_path = temp_dir();
-    file_path.push("editable");
-    let mut file = File::create(&file_path).expect("Could not create file");
-
-    let bookmark = proxy.bookmark(id).unwrap();
-
-    let editable_content = format!(
-        "# Description:\n{}\n\n# Url:\n{}\n\n# Tags:\n{}",
-        bookmark.bookmark.description,
-        bookmark.bookmark.url,
-        intersperse(
-            bookmark.tags.iter().map(|tag| tag.tag.to_string()),
-            ",".to_string()
-        )
-        .collect::<String>()
-    );
-
-    file.write_all(editable_content.as_bytes()).unwrap();
-
-    args.push(file_path.to_str().unwrap().to_string());
-    Command::new(editor)
-        .args(args)
-        .status()
-        .expect("Something went wrong");
-
-    let mut editable = String::new();
-
-    File::open(file_path)
-        .expect("Could not open file")
-        .read_to_string(&mut editable)
-        .unwrap();
-
-    let lines = editable.lines().collect::<Vec<&str>>();
-
-    let description = lines[1];
-    let url = lines[4];
-
-    proxy.update_url(id, url).unwrap();
-    proxy.update_description(id, description).unwrap();
-    if lines.len() == 8 {
-        let tags = lines[7];
-        proxy
-            .update_tags(
-                id,
-                &tags
-                    .split(',')
-                    .map(|x| x.to_string())
-                    .collect::<Vec<String>>(),
-            )
-            .unwrap();
-    }
+    /// Bookmarks that were deleted but not yet purged
+    fn trash(&self) -> Result<Vec<Bookmark>, String>;
+    fn restore(&self, id: i32) -> Result<Bookmark, String>;
+    /// Permanently remove every bookmark in the trash, returning how many were removed
+    fn empty_trash(&self) -> Result<usize, String>;
+    /// Revisions of a bookmark, oldest first
+    fn history(&self, id: i32) -> Result<Vec<Revision>, String>;
+    /// Set a bookmark's url, description and tags to those of revision `rev`
+    fn revert(&self, id: i32, rev: i32) -> Result<Bookmark, String>;
+    /// Reverse the most recent change to any bookmark, returning the undone revision
+    fn undo(&self) -> Result<Revision, String>;
+    /// Store the outcome of checking a bookmark's url, replacing the previous one
+    fn record_check(&self, check: &LinkCheck) -> Result<(), String>;
+    /// Latest check of every bookmark that is not in the trash
+    fn link_checks(&self) -> Result<Vec<LinkCheck>, String>;
+    /// Archive `content` as a copy of the page of bookmark `id`
+    fn store_snapshot(&self, id: i32, content: &str) -> Result<Snapshot, String>;
+    /// Snapshots of a bookmark, newest first
+    fn snapshots(&self, id: i32) -> Result<Vec<Snapshot>, String>;
+    /// Stored html of the snapshot identified by `hash`
+    fn snapshot(&self, hash: &str) -> Result<String, String>;
+    /// Index the readable text of the page of bookmark `id`, replacing the previous one
+    fn set_page_text(&self, id: i32, text: &str) -> Result<(), String>;
+    /// Bookmarks whose description, url, tags or page text contain every word of `query`,
+    /// the closest matches of the description, url and tags first
+    fn search(&self, query: &str) -> Result<Vec<SearchResult>, String>;
+    /// Record that bookmark `id` was opened
+    fn visit(&self, id: i32) -> Result<(), String>;
+    /// Visits of every bookmark that was opened and is not in the trash
+    fn visits(&self) -> Result<Vec<Visits>, String>;
+    /// Tags in use by bookmarks that are not in the trash, alphabetically
+    fn tags(&self) -> Result<Vec<TagCount>, String>;
 }
@@ -0,0 +1,225 @@
+// Copyright 2024 João Freitas
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of
+// the GNU General Public License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS FOR
+// A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Requests bookmark urls to find the ones that stopped working.
+
+use reqwest::blocking::Client;
+use reqwest::header::LOCATION;
+use reqwest::redirect::Policy;
+use url::Url;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use crate::bookmark::{Bookmark, LinkCheck};
+use crate::bookmark_proxy::BookmarkProxy;
+use crate::config::CheckConfig;
+use crate::local_proxy::now;
+
+fn client(timeout: Duration) -> Result<Client, String> {
+    Client::builder()
+        .timeout(timeout)
+        .redirect(Policy::none())
+        .user_agent(concat!("marcador/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|err| format!("{}", err))
+}
+
+/// Whether `url` can be checked, only http and https urls are requested.
+pub fn is_checkable(url: &str) -> bool {
+    Url::parse(url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+/// Request the url of `bookmark` without following redirects.
+fn check_url(client: &Client, bookmark: &Bookmark) -> LinkCheck {
+    let url = &bookmark.bookmark.url;
+    let mut check = LinkCheck {
+        bookmark_id: bookmark.bookmark.id,
+        url: url.clone(),
+        status: None,
+        redirect: None,
+        error: None,
+        checked_at: now(),
+    };
+
+    match client.get(url).send() {
+        Ok(response) => {
+            check.status = Some(response.status().as_u16() as i32);
+            check.redirect = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| Url::parse(url).ok()?.join(location).ok())
+                .map(|location| location.to_string());
+        }
+        Err(err) if err.is_timeout() => check.error = Some("Timed out".to_string()),
+        Err(err) if err.is_connect() => check.error = Some("Failed to connect".to_string()),
+        Err(err) => check.error = Some(format!("{}", err)),
+    }
+
+    check
+}
+
+/// Request the urls of `bookmarks`, `concurrency` at a time. Checks are
+/// returned in the order of `bookmarks`, urls that aren't checkable are
+/// skipped.
+pub fn check_urls(
+    bookmarks: &[Bookmark],
+    timeout: Duration,
+    concurrency: usize,
+) -> Result<Vec<LinkCheck>, String> {
+    let client = client(timeout)?;
+    let bookmarks: Vec<&Bookmark> = bookmarks
+        .iter()
+        .filter(|bookmark| is_checkable(&bookmark.bookmark.url))
+        .collect();
+    let next = AtomicUsize::new(0);
+
+    let mut checks: Vec<(usize, LinkCheck)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..concurrency.clamp(1, bookmarks.len().max(1)))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut checks = vec![];
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        match bookmarks.get(index) {
+                            Some(bookmark) => checks.push((index, check_url(&client, bookmark))),
+                            None => return checks,
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap_or_default())
+            .collect()
+    });
+
+    checks.sort_by_key(|(index, _)| *index);
+    Ok(checks.into_iter().map(|(_, check)| check).collect())
+}
+
+/// Check the urls of `bookmarks` and record the outcome through `proxy`.
+pub fn check(
+    proxy: &dyn BookmarkProxy,
+    bookmarks: &[Bookmark],
+    config: &CheckConfig,
+) -> Result<Vec<LinkCheck>, String> {
+    let checks = check_urls(
+        bookmarks,
+        Duration::from_secs(config.get_timeout()),
+        config.get_concurrency(),
+    )?;
+
+    for check in &checks {
+        proxy.record_check(check)?;
+    }
+
+    Ok(checks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::models::Bookmarks;
+
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    /// Answer requests to `/ok`, `/moved`, `/found` and anything else with
+    /// 200, 301, 302 and 404 respectively.
+    fn serve() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = String::new();
+                BufReader::new(&stream).read_line(&mut request).unwrap();
+                let response = match request.split_whitespace().nth(1) {
+                    Some("/ok") => "200 OK\r\n".to_string(),
+                    Some("/moved") => "301 Moved Permanently\r\nLocation: /ok\r\n".to_string(),
+                    Some("/found") => "302 Found\r\nLocation: http://example.com/\r\n".to_string(),
+                    _ => "404 Not Found\r\n".to_string(),
+                };
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {}Content-Length: 0\r\nConnection: close\r\n\r\n",
+                    response
+                );
+            }
+        });
+
+        format!("http://{}", addr)
+    }
+
+    fn bookmark(id: i32, url: &str) -> Bookmark {
+        Bookmark::new(
+            &Bookmarks {
+                id,
+                url: url.to_string(),
+                description: String::new(),
+                deleted_at: None,
+                metadata: None,
+            },
+            &[],
+        )
+    }
+
+    #[test]
+    fn urls_are_checked() {
+        let base = serve();
+        let bookmarks = vec![
+            bookmark(1, &format!("{}/ok", base)),
+            bookmark(2, &format!("{}/moved", base)),
+            bookmark(3, &format!("{}/found", base)),
+            bookmark(4, &format!("{}/gone", base)),
+            bookmark(5, "http://127.0.0.1:1/"),
+            bookmark(6, "ssh://box"),
+            bookmark(7, "mailto:me@example.com"),
+            bookmark(8, "file:///etc/hosts"),
+        ];
+
+        let checks = check_urls(&bookmarks, Duration::from_secs(5), 2).unwrap();
+        let summary: Vec<(i32, Option<i32>, bool, Option<&str>)> = checks
+            .iter()
+            .map(|check| {
+                (
+                    check.bookmark_id,
+                    check.status,
+                    check.is_broken(),
+                    check.moved_to(),
+                )
+            })
+            .collect();
+
+        let moved = format!("{}/ok", base);
+        assert_eq!(
+            summary,
+            vec![
+                (1, Some(200), false, None),
+                (2, Some(301), false, Some(moved.as_str())),
+                (3, Some(302), false, None),
+                (4, Some(404), true, None),
+                (5, None, true, None),
+            ]
+        );
+        assert_eq!(checks[2].redirect.as_deref(), Some("http://example.com/"));
+        assert!(checks[4].error.is_some());
+    }
+}
@@ -0,0 +1,112 @@
+// Copyright 2024 João Freitas
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of
+// the GNU General Public License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS FOR
+// A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! The system clipboard, shared by the interfaces.
+//!
+//! On X11 and Wayland the clipboard is served by the program that set it, so
+//! text is copied with a tool that keeps serving it after marcador exits.
+
+use copypasta::{ClipboardContext, ClipboardProvider};
+use url::Url;
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Commands reading the text to copy from stdin, tried in order
+const COPY_COMMANDS: &[&[&str]] = &[
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard"],
+    &["xsel", "--clipboard", "--input"],
+];
+
+/// Copy `text` with the first of `commands` that runs and succeeds.
+fn copy_with(commands: &[&[&str]], text: &str) -> bool {
+    commands.iter().any(|command| {
+        let child = Command::new(command[0])
+            .args(&command[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(_) => return false,
+        };
+        let written = child
+            .stdin
+            .take()
+            .is_some_and(|mut stdin| stdin.write_all(text.as_bytes()).is_ok());
+        child.wait().is_ok_and(|status| status.success()) && written
+    })
+}
+
+/// Replace the contents of the clipboard with `text`.
+pub fn copy(text: String) -> Result<(), String> {
+    if copy_with(COPY_COMMANDS, &text) {
+        return Ok(());
+    }
+
+    let mut ctx = ClipboardContext::new().map_err(|_| "Failed to create clipboard context")?;
+    ctx.set_contents(text)
+        .map_err(|_| "Failed to set clipboard contents".to_string())
+}
+
+/// Url held by the clipboard, none if it holds anything else.
+pub fn url() -> Option<String> {
+    let mut ctx = ClipboardContext::new().ok()?;
+    as_url(&ctx.get_contents().ok()?)
+}
+
+/// Trimmed `text` if it is an url with a host.
+pub fn as_url(text: &str) -> Option<String> {
+    let text = text.trim();
+    Url::parse(text)
+        .ok()
+        .filter(|url| url.has_host())
+        .map(|_| text.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_urls_are_accepted() {
+        assert_eq!(
+            as_url("  https://a.com/page\n"),
+            Some("https://a.com/page".to_string())
+        );
+        assert_eq!(as_url("ssh://box"), Some("ssh://box".to_string()));
+        assert_eq!(as_url("some copied text"), None);
+        assert_eq!(as_url("mailto:me@a.com"), None);
+        assert_eq!(as_url(""), None);
+    }
+
+    #[test]
+    fn text_is_piped_to_the_first_working_command() {
+        let dir = tempfile::tempdir().unwrap();
+        let copied = dir.path().join("copied");
+        let script = format!("cat > '{}'", copied.display());
+
+        assert!(copy_with(
+            &[
+                &["marcador-missing-clipboard"],
+                &["false"],
+                &["sh", "-c", &script],
+            ],
+            "https://a.com",
+        ));
+        assert_eq!(std::fs::read_to_string(&copied).unwrap(), "https://a.com");
+        assert!(!copy_with(&[&["false"]], "https://a.com"));
+    }
+}
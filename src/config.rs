@@ -14,6 +14,8 @@
 use directories::BaseDirs;
 use serde::Deserialize;
 
+use crate::normalise::default_tracking_params;
+
 use std::fs;
 
 #[derive(Deserialize, Default)]
@@ -22,6 +24,8 @@ pub struct ServerConfig {
     pub host: Option<String>,
     pub port: Option<u16>,
     pub root: Option<String>,
+    /// Hours between checks of every bookmark url, never checked if unset
+    pub check_interval_hours: Option<u64>,
 }
 
 impl ServerConfig {
@@ -51,11 +55,7 @@ impl ServerConfig {
     }
 
     pub fn get_port(&self) -> u16 {
-        if let Some(port) = self.port {
-            port
-        } else {
-            8080
-        }
+        self.port.unwrap_or(8080)
     }
 
     pub fn set_root(&mut self, root: &Option<String>) {
@@ -73,10 +73,196 @@ impl ServerConfig {
     }
 }
 
+/// Options of the dead link checker
+#[derive(Deserialize, Default, Clone)]
+pub struct CheckConfig {
+    /// Seconds to wait for each url
+    pub timeout: Option<u64>,
+    /// Number of urls requested at the same time
+    pub concurrency: Option<usize>,
+}
+
+impl CheckConfig {
+    pub fn set_timeout(&mut self, timeout: &Option<u64>) {
+        if timeout.is_some() {
+            self.timeout = *timeout;
+        }
+    }
+
+    pub fn get_timeout(&self) -> u64 {
+        self.timeout.unwrap_or(10)
+    }
+
+    pub fn set_concurrency(&mut self, concurrency: &Option<usize>) {
+        if concurrency.is_some() {
+            self.concurrency = *concurrency;
+        }
+    }
+
+    pub fn get_concurrency(&self) -> usize {
+        self.concurrency.unwrap_or(8).max(1)
+    }
+}
+
+/// Command used to open bookmarks that match every given condition
+#[derive(Deserialize, Default, Clone, Debug, PartialEq, Eq)]
+pub struct OpenRule {
+    pub tag: Option<String>,
+    /// Host of the url, subdomains included
+    pub domain: Option<String>,
+    pub scheme: Option<String>,
+    pub command: String,
+}
+
+/// How bookmarks are opened
+#[derive(Deserialize, Default, Clone, Debug)]
+pub struct OpenConfig {
+    /// Command template used when no rule matches, the system handler if unset
+    pub command: Option<String>,
+    /// Rules tried in order before the default command
+    #[serde(default)]
+    pub rules: Vec<OpenRule>,
+}
+
+/// Program showing the bookmark menus
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LauncherKind {
+    #[default]
+    Rofi,
+    Dmenu,
+    Wofi,
+    Fuzzel,
+    Bemenu,
+    Fzf,
+}
+
+/// What a rofi keybinding does with the selected bookmarks
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RofiAction {
+    Add,
+    Open,
+    CopyUrl,
+    CopyMarkdown,
+    Edit,
+    Delete,
+    Tag,
+    Untag,
+    OpenArchived,
+    /// Go back to the tags in the tag menu
+    Back,
+}
+
+impl RofiAction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RofiAction::Add => "Add new bookmark",
+            RofiAction::Open => "Open",
+            RofiAction::CopyUrl => "Copy url",
+            RofiAction::CopyMarkdown => "Copy markdown link",
+            RofiAction::Edit => "Edit",
+            RofiAction::Delete => "Delete",
+            RofiAction::Tag => "Add tags",
+            RofiAction::Untag => "Remove tags",
+            RofiAction::OpenArchived => "Open offline copy",
+            RofiAction::Back => "Back to tags",
+        }
+    }
+}
+
+/// Keybinding of the rofi menu
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RofiBinding {
+    /// Rofi key combination, like `Alt+n`
+    pub key: String,
+    /// Text shown in the help message, the action name by default
+    pub label: Option<String>,
+    pub action: RofiAction,
+}
+
+impl RofiBinding {
+    pub fn new(key: &str, action: RofiAction) -> RofiBinding {
+        RofiBinding {
+            key: key.to_string(),
+            label: None,
+            action,
+        }
+    }
+
+    pub fn get_label(&self) -> &str {
+        self.label.as_deref().unwrap_or(self.action.label())
+    }
+}
+
+/// Appearance of the rofi menu
+#[derive(Deserialize, Default, Clone)]
+pub struct RofiConfig {
+    /// Pango markup of each bookmark row, with `{description}`, `{domain}`,
+    /// `{url}`, `{tags}` and `{id}` placeholders
+    pub row: Option<String>,
+    /// Color of the tags in rows
+    pub tag_color: Option<String>,
+    /// Keybindings of the bookmark menu, Return always opens
+    pub actions: Option<Vec<RofiBinding>>,
+    /// Show the favicons of bookmarked sites
+    pub icons: Option<bool>,
+    /// Download favicons that aren't cached yet
+    pub fetch_icons: Option<bool>,
+}
+
+impl RofiConfig {
+    pub fn get_row(&self) -> String {
+        self.row
+            .clone()
+            .unwrap_or_else(|| "{description}  {domain}  {tags}".to_string())
+    }
+
+    pub fn get_tag_color(&self) -> String {
+        self.tag_color
+            .clone()
+            .unwrap_or_else(|| "#5e81ac".to_string())
+    }
+
+    pub fn get_icons(&self) -> bool {
+        self.icons.unwrap_or(false)
+    }
+
+    pub fn get_fetch_icons(&self) -> bool {
+        self.fetch_icons.unwrap_or(true)
+    }
+
+    pub fn get_actions(&self) -> Vec<RofiBinding> {
+        self.actions.clone().unwrap_or_else(|| {
+            vec![
+                RofiBinding::new("Alt+n", RofiAction::Add),
+                RofiBinding::new("Alt+d", RofiAction::Delete),
+                RofiBinding::new("Alt+e", RofiAction::Edit),
+                RofiBinding::new("Alt+t", RofiAction::Tag),
+                RofiBinding::new("Alt+r", RofiAction::Untag),
+                RofiBinding::new("Alt+c", RofiAction::CopyUrl),
+                RofiBinding::new("Alt+BackSpace", RofiAction::Back),
+            ]
+        })
+    }
+}
+
 #[derive(Deserialize)]
 pub struct Config {
     pub host: Option<String>,
     pub db: Option<String>,
+    /// Query parameters ignored when comparing urls
+    pub tracking_params: Option<Vec<String>>,
+    /// Days after which deleted bookmarks are purged from the trash
+    pub trash_retention_days: Option<u64>,
+    /// Fetch the title, description and favicon of pages when adding them, off
+    /// by default as it contacts every bookmarked site
+    pub fetch_metadata: Option<bool>,
+    pub check: Option<CheckConfig>,
+    pub open: Option<OpenConfig>,
+    /// Program showing the menus of `marcador rofi`
+    pub launcher: Option<LauncherKind>,
+    pub rofi: Option<RofiConfig>,
     pub server: Option<ServerConfig>,
 }
 
@@ -85,6 +271,13 @@ impl Default for Config {
         Config {
             host: None,
             db: None,
+            tracking_params: None,
+            trash_retention_days: None,
+            fetch_metadata: None,
+            check: None,
+            open: None,
+            launcher: None,
+            rofi: None,
             server: Some(ServerConfig::default()),
         }
     }
@@ -116,4 +309,18 @@ impl Config {
             self.db = db.clone();
         }
     }
+
+    pub fn get_tracking_params(&self) -> Vec<String> {
+        self.tracking_params
+            .clone()
+            .unwrap_or_else(default_tracking_params)
+    }
+
+    pub fn get_fetch_metadata(&self) -> bool {
+        self.fetch_metadata.unwrap_or(false)
+    }
+
+    pub fn get_launcher(&self) -> LauncherKind {
+        self.launcher.unwrap_or_default()
+    }
 }
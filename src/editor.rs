@@ -0,0 +1,524 @@
+// Copyright 2024 João Freitas
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of
+// the GNU General Public License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS FOR
+// A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Editing bookmarks as toml documents in the user's editor.
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use std::{env::var, fs, io::Write, process::Command};
+
+use crate::bookmark::{Bookmark, NewBookmark};
+use crate::bookmark_proxy::BookmarkProxy;
+use crate::metadata::PageMetadata;
+
+const HEADER: &str = "\
+# Edit the bookmarks and save to apply the changes. Removing a [[bookmark]]
+# deletes it, adding one without an id creates a new bookmark.
+# Lines starting with # are ignored, an empty file cancels the edit.
+";
+
+/// Prefix of the comment lines describing why the previous edit was rejected.
+const ERROR_PREFIX: &str = "#! ";
+
+/// The fields of a bookmark that can be edited
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct EditableBookmark {
+    /// Id of an existing bookmark, new bookmarks have none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<i32>,
+    pub url: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl EditableBookmark {
+    pub fn new(bookmark: &Bookmark) -> EditableBookmark {
+        EditableBookmark {
+            id: Some(bookmark.bookmark.id),
+            url: bookmark.bookmark.url.clone(),
+            description: bookmark.bookmark.description.clone(),
+            tags: bookmark.tags.iter().map(|tag| tag.tag.clone()).collect(),
+        }
+    }
+
+    /// Input to add this bookmark as a new one
+    pub fn new_bookmark(&self) -> NewBookmark {
+        NewBookmark {
+            url: self.url.clone(),
+            description: self.description.clone(),
+            tags: self.tags.clone(),
+            metadata: None,
+        }
+    }
+
+    /// Trim every field, drop empty and repeated tags and check that the url
+    /// can be parsed.
+    pub fn validate(self) -> Result<EditableBookmark, String> {
+        let url = self.url.trim().to_string();
+        if url.is_empty() {
+            return Err("url must not be empty".to_string());
+        }
+        Url::parse(&url).map_err(|err| format!("invalid url {:?}: {}", url, err))?;
+
+        Ok(EditableBookmark {
+            id: self.id,
+            url,
+            description: self.description.trim().to_string(),
+            tags: self
+                .tags
+                .iter()
+                .map(|tag| tag.trim().to_string())
+                .filter(|tag| !tag.is_empty())
+                .unique()
+                .collect(),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct Document {
+    #[serde(default)]
+    bookmark: Vec<EditableBookmark>,
+}
+
+/// Render bookmarks as the document shown in the editor.
+pub fn render(bookmarks: &[EditableBookmark]) -> Result<String, String> {
+    let document = Document {
+        bookmark: bookmarks.to_vec(),
+    };
+    Ok(HEADER.to_string() + &toml::to_string(&document).map_err(|err| format!("{}", err))?)
+}
+
+/// Parse an edited document, `None` means the edit was cancelled.
+pub fn parse(text: &str) -> Result<Option<Vec<EditableBookmark>>, String> {
+    if is_blank(text) {
+        return Ok(None);
+    }
+
+    let document =
+        toml::from_str::<Document>(text).map_err(|err| err.message().trim().to_string())?;
+
+    let mut ids: Vec<i32> = document
+        .bookmark
+        .iter()
+        .filter_map(|bookmark| bookmark.id)
+        .collect();
+    ids.sort();
+    if let Some((id, _)) = ids.iter().tuple_windows().find(|(a, b)| a == b) {
+        return Err(format!("bookmark {} appears more than once", id));
+    }
+
+    document
+        .bookmark
+        .into_iter()
+        .map(EditableBookmark::validate)
+        .collect::<Result<Vec<EditableBookmark>, String>>()
+        .map(Some)
+}
+
+/// Whether a document holds nothing but comments and whitespace.
+pub fn is_blank(text: &str) -> bool {
+    text.lines()
+        .map(str::trim)
+        .all(|line| line.is_empty() || line.starts_with('#'))
+}
+
+fn editor(visual: bool) -> Vec<String> {
+    let editor = if visual {
+        var("VISUAL").or_else(|_| var("EDITOR"))
+    } else {
+        var("EDITOR").or_else(|_| var("VISUAL"))
+    };
+
+    editor
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string())
+        .split_whitespace()
+        .map(|arg| arg.to_string())
+        .collect()
+}
+
+/// Let the user edit `text` in a temporary file and return the result.
+/// `visual` prefers `$VISUAL` over `$EDITOR`.
+pub fn edit_text(text: &str, visual: bool) -> Result<String, String> {
+    let mut file = tempfile::Builder::new()
+        .prefix("marcador-")
+        .suffix(".toml")
+        .tempfile()
+        .map_err(|err| format!("Could not create temporary file: {}", err))?;
+    file.write_all(text.as_bytes())
+        .map_err(|err| format!("Could not write temporary file: {}", err))?;
+
+    let editor = editor(visual);
+    let status = Command::new(&editor[0])
+        .args(&editor[1..])
+        .arg(file.path())
+        .status()
+        .map_err(|err| format!("Failed to run {}: {}", editor[0], err))?;
+    if !status.success() {
+        return Err(format!("{} exited with {}", editor[0], status));
+    }
+
+    fs::read_to_string(file.path()).map_err(|err| format!("Could not read temporary file: {}", err))
+}
+
+/// Prepend `error` to a rejected document so the user can fix it, replacing
+/// the error of a previous attempt.
+pub fn annotate(text: &str, error: &str) -> String {
+    let errors = error
+        .lines()
+        .map(|line| format!("{}{}\n", ERROR_PREFIX, line))
+        .collect::<String>();
+    let text = text
+        .lines()
+        .filter(|line| !line.starts_with(ERROR_PREFIX))
+        .map(|line| format!("{}\n", line))
+        .collect::<String>();
+
+    errors + &text
+}
+
+/// Changes between the bookmarks shown in the editor and the saved document
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Changes {
+    /// Pairs of original and edited bookmarks that differ
+    pub updated: Vec<(EditableBookmark, EditableBookmark)>,
+    pub added: Vec<EditableBookmark>,
+    pub deleted: Vec<i32>,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.updated.is_empty() && self.added.is_empty() && self.deleted.is_empty()
+    }
+}
+
+/// Compare the bookmarks shown in the editor with the edited ones.
+pub fn diff(original: &[EditableBookmark], edited: &[EditableBookmark]) -> Result<Changes, String> {
+    let mut changes = Changes::default();
+
+    for bookmark in edited {
+        match bookmark.id {
+            None => changes.added.push(bookmark.clone()),
+            Some(id) => {
+                let before = original
+                    .iter()
+                    .find(|before| before.id == Some(id))
+                    .ok_or(format!("bookmark {} was not being edited", id))?;
+                if before != bookmark {
+                    changes.updated.push((before.clone(), bookmark.clone()));
+                }
+            }
+        }
+    }
+
+    changes.deleted = original
+        .iter()
+        .filter_map(|before| before.id)
+        .filter(|id| !edited.iter().any(|bookmark| bookmark.id == Some(*id)))
+        .collect();
+
+    Ok(changes)
+}
+
+/// Bookmarks being edited as they are stored, and what was applied so far
+struct Session<'a> {
+    proxy: &'a dyn BookmarkProxy,
+    stored: Vec<EditableBookmark>,
+    applied: Changes,
+    added: Vec<Bookmark>,
+}
+
+impl Session<'_> {
+    /// Apply the changes from the stored bookmarks to `edited` through the
+    /// proxy, only touching the fields that changed. The stored bookmarks follow
+    /// every change and added bookmarks get their id in `edited`, so that after
+    /// an error the document can be edited again and the rest applied.
+    fn apply(
+        &mut self,
+        edited: &mut [EditableBookmark],
+        new_bookmark: &dyn Fn(&EditableBookmark) -> NewBookmark,
+    ) -> Result<(), String> {
+        let proxy = self.proxy;
+        let changes = diff(&self.stored, edited)?;
+
+        for id in changes.deleted {
+            proxy.delete(id)?;
+            self.stored.retain(|bookmark| bookmark.id != Some(id));
+            self.applied.deleted.push(id);
+        }
+
+        for (before, after) in changes.updated {
+            let id = before.id.ok_or("Updated bookmark without id")?;
+            let stored = self
+                .stored
+                .iter_mut()
+                .find(|bookmark| bookmark.id == Some(id))
+                .ok_or(format!("bookmark {} was not being edited", id))?;
+            if after.url != stored.url {
+                proxy.update_url(id, &after.url)?;
+                stored.url = after.url.clone();
+            }
+            if after.description != stored.description {
+                proxy.update_description(id, &after.description)?;
+                stored.description = after.description.clone();
+            }
+            if after.tags != stored.tags {
+                proxy.update_tags(id, &after.tags)?;
+                stored.tags = after.tags.clone();
+            }
+            self.applied.updated.push((before, after));
+        }
+
+        for bookmark in edited.iter_mut().filter(|bookmark| bookmark.id.is_none()) {
+            let added = proxy.add(&new_bookmark(bookmark))?;
+            bookmark.id = Some(added.bookmark.id);
+            self.stored.push(bookmark.clone());
+            self.applied.added.push(bookmark.clone());
+            self.added.push(added);
+        }
+
+        Ok(())
+    }
+}
+
+/// Open `shown` in the editor and apply the changes from `original` until the
+/// document is valid and every change is applied, showing the error at the top
+/// of the document each time it is re-opened. Returns the applied changes and
+/// the added bookmarks.
+fn edit_and_apply(
+    proxy: &dyn BookmarkProxy,
+    original: &[EditableBookmark],
+    shown: &[EditableBookmark],
+    visual: bool,
+    new_bookmark: &dyn Fn(&EditableBookmark) -> NewBookmark,
+) -> Result<(Changes, Vec<Bookmark>), String> {
+    let mut session = Session {
+        proxy,
+        stored: original.to_vec(),
+        applied: Changes::default(),
+        added: vec![],
+    };
+
+    let mut text = render(shown)?;
+    loop {
+        let edited_text = edit_text(&text, visual)?;
+        let mut edited = match parse(&edited_text) {
+            Ok(Some(edited)) => edited,
+            Ok(None) => break,
+            Err(err) => {
+                text = annotate(&edited_text, &err);
+                continue;
+            }
+        };
+        match session.apply(&mut edited, new_bookmark) {
+            Ok(()) => break,
+            Err(err) => text = annotate(&render(&edited)?, &err),
+        }
+    }
+
+    Ok((session.applied, session.added))
+}
+
+/// Edit several bookmarks in one editor session and apply the result.
+pub fn edit_bookmarks(
+    proxy: &dyn BookmarkProxy,
+    bookmarks: &[Bookmark],
+    visual: bool,
+) -> Result<Changes, String> {
+    let original: Vec<EditableBookmark> = bookmarks.iter().map(EditableBookmark::new).collect();
+
+    let (changes, _) = edit_and_apply(
+        proxy,
+        &original,
+        &original,
+        visual,
+        &EditableBookmark::new_bookmark,
+    )?;
+    Ok(changes)
+}
+
+/// Let the user complete `template` in the editor and add the result.
+/// `metadata` is kept for bookmarks still pointing to the template url.
+/// Returns the added bookmarks, none if the edit was cancelled.
+pub fn add_bookmarks(
+    proxy: &dyn BookmarkProxy,
+    template: &EditableBookmark,
+    metadata: Option<&PageMetadata>,
+    visual: bool,
+) -> Result<Vec<Bookmark>, String> {
+    let (_, added) = edit_and_apply(
+        proxy,
+        &[],
+        std::slice::from_ref(template),
+        visual,
+        &|bookmark| NewBookmark {
+            metadata: metadata.filter(|_| bookmark.url == template.url).cloned(),
+            ..bookmark.new_bookmark()
+        },
+    )?;
+    Ok(added)
+}
+
+/// Edit bookmark `id` in the user's editor. Returns whether it was changed.
+pub fn edit_bookmark(proxy: &dyn BookmarkProxy, id: i32, visual: bool) -> Result<bool, String> {
+    let changes = edit_bookmarks(proxy, &[proxy.bookmark(id)?], visual)?;
+    Ok(!changes.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::local_proxy::LocalProxy;
+
+    fn bookmark(id: Option<i32>, url: &str) -> EditableBookmark {
+        EditableBookmark {
+            id,
+            url: url.to_string(),
+            description: "An \"example\"\nspanning lines".to_string(),
+            tags: vec!["a".to_string(), "b".to_string()],
+        }
+    }
+
+    #[test]
+    fn rendered_bookmarks_parse_back() {
+        let bookmarks = vec![
+            bookmark(Some(1), "https://one.com"),
+            bookmark(Some(2), "https://two.com"),
+        ];
+        let text = render(&bookmarks).unwrap();
+        assert_eq!(parse(&text).unwrap(), Some(bookmarks));
+    }
+
+    #[test]
+    fn fields_are_found_by_key() {
+        let text = "[[bookmark]]\ntags = [' b ', 'a', 'b', '']\n# comment\nurl = ' https://x.com '";
+        assert_eq!(
+            parse(text).unwrap(),
+            Some(vec![EditableBookmark {
+                id: None,
+                url: "https://x.com".to_string(),
+                description: String::new(),
+                tags: vec!["b".to_string(), "a".to_string()],
+            }])
+        );
+    }
+
+    #[test]
+    fn empty_documents_cancel() {
+        assert_eq!(parse(HEADER).unwrap(), None);
+        assert_eq!(parse("\n  \n").unwrap(), None);
+    }
+
+    #[test]
+    fn invalid_documents_are_rejected() {
+        for text in [
+            "[[bookmark]]\nurl = ''",
+            "[[bookmark]]\nurl = 'example.com'",
+            "[[bookmark]]\ndescription = 'no url'",
+            "[[bookmark]]\nurl = 'https://x.com'\ncolour = 'red'",
+            "[[bookmark]]\nurl = https://x.com",
+            "[[bookmark]]\nid = 1\nurl = 'https://x.com'\n[[bookmark]]\nid = 1\nurl = 'https://y.com'",
+        ] {
+            assert!(parse(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn diff_finds_updates_additions_and_deletions() {
+        let original = vec![
+            bookmark(Some(1), "https://one.com"),
+            bookmark(Some(2), "https://two.com"),
+            bookmark(Some(3), "https://three.com"),
+        ];
+        let edited = vec![
+            bookmark(Some(1), "https://one.com"),
+            bookmark(Some(3), "https://three.org"),
+            bookmark(None, "https://four.com"),
+        ];
+
+        assert_eq!(
+            diff(&original, &edited).unwrap(),
+            Changes {
+                updated: vec![(original[2].clone(), edited[1].clone())],
+                added: vec![edited[2].clone()],
+                deleted: vec![2],
+            }
+        );
+        assert!(diff(&original, &original).unwrap().is_empty());
+        assert!(diff(&original[..1], &edited).is_err());
+    }
+
+    #[test]
+    fn failed_changes_can_be_fixed_and_applied() {
+        let dir = tempfile::tempdir().unwrap();
+        let proxy = LocalProxy::new(dir.path().join("marcador.db").to_str().unwrap());
+        let original: Vec<EditableBookmark> = ["https://one.com", "https://two.com"]
+            .iter()
+            .map(|url| {
+                EditableBookmark::new(&proxy.add(&bookmark(None, url).new_bookmark()).unwrap())
+            })
+            .collect();
+        let mut session = Session {
+            proxy: &proxy,
+            stored: original.clone(),
+            applied: Changes::default(),
+            added: vec![],
+        };
+
+        let mut edited = original.clone();
+        edited[0].description = "One".to_string();
+        edited[1].url = "https://one.com".to_string();
+        edited.push(bookmark(None, "https://three.com"));
+        edited.push(bookmark(None, "https://one.com/"));
+        assert!(session
+            .apply(&mut edited, &EditableBookmark::new_bookmark)
+            .is_err());
+        assert_eq!(proxy.bookmark(1).unwrap().bookmark.description, "One");
+        assert_eq!(edited[2].id, None);
+
+        edited[1].url = "https://two.org".to_string();
+        assert!(session
+            .apply(&mut edited, &EditableBookmark::new_bookmark)
+            .is_err());
+        assert_eq!(edited[2].id, Some(3));
+
+        edited[3].url = "https://four.com".to_string();
+        session
+            .apply(&mut edited, &EditableBookmark::new_bookmark)
+            .unwrap();
+        assert_eq!(session.applied.updated.len(), 2);
+        assert_eq!(session.applied.added.len(), 2);
+        assert_eq!(session.added.len(), 2);
+        assert_eq!(proxy.bookmark(2).unwrap().bookmark.url, "https://two.org");
+        assert_eq!(proxy.bookmarks().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn annotations_replace_previous_errors() {
+        let text = annotate("[[bookmark]]\nurl = ''\n", "url must not be empty");
+        assert_eq!(text, "#! url must not be empty\n[[bookmark]]\nurl = ''\n");
+
+        let text = annotate(&text, "first\nsecond");
+        assert_eq!(text, "#! first\n#! second\n[[bookmark]]\nurl = ''\n");
+        assert!(parse(&text).is_err());
+    }
+}
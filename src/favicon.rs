@@ -0,0 +1,471 @@
+// Copyright 2024 João Freitas
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of
+// the GNU General Public License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS FOR
+// A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Favicons of bookmarked sites, cached per host to show as rofi icons.
+//!
+//! Icons are kept in `$XDG_CACHE_HOME/marcador/favicons` in files named after
+//! the host. Hosts whose icon can't be downloaded are remembered for a week so
+//! they aren't requested every time the menu opens. Menus only show cached
+//! icons, missing ones are downloaded by a detached `marcador fetch-icons` for
+//! the next run.
+
+use directories::BaseDirs;
+use reqwest::blocking::Client;
+use reqwest::header::CONTENT_TYPE;
+use url::Url;
+
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufRead, Read, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::bookmark::Bookmark;
+
+const TIMEOUT: Duration = Duration::from_secs(3);
+const CONCURRENCY: usize = 8;
+/// Bytes of an icon downloaded at most
+const MAX_ICON_SIZE: u64 = 1024 * 1024;
+/// How long a host whose icon failed to download is left alone
+const RETRY_AFTER: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+/// Extension of the files marking failed downloads
+const MISSING: &str = "missing";
+/// File held while icons are downloaded, without an extension so it isn't
+/// taken for an icon
+const LOCK: &str = "fetching";
+/// Age after which a lock is assumed to be left over by a crashed download
+const STALE_LOCK: Duration = Duration::from_secs(10 * 60);
+
+/// Lock on the cache directory, released when dropped
+struct Lock(PathBuf);
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// Host, with the port if any, under which the icon of `url` is cached.
+fn host(url: &Url) -> Option<String> {
+    let host = url.host_str()?;
+    Some(match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    })
+}
+
+/// Extension for an icon served as `mime` from `url`.
+fn extension(mime: Option<&str>, url: &Url) -> String {
+    let from_mime = mime.and_then(|mime| match mime.split(';').next()?.trim() {
+        "image/png" => Some("png"),
+        "image/svg+xml" => Some("svg"),
+        "image/gif" => Some("gif"),
+        "image/jpeg" => Some("jpg"),
+        "image/webp" => Some("webp"),
+        "image/x-icon" | "image/vnd.microsoft.icon" => Some("ico"),
+        _ => None,
+    });
+    let from_path = || {
+        let extension = url.path().rsplit_once('.')?.1.to_lowercase();
+        ["png", "svg", "gif", "jpg", "jpeg", "webp", "ico"]
+            .contains(&extension.as_str())
+            .then_some(extension)
+    };
+
+    from_mime
+        .map(str::to_string)
+        .or_else(from_path)
+        .unwrap_or_else(|| "ico".to_string())
+}
+
+/// Directory of favicons named after their host
+pub struct FaviconCache {
+    dir: PathBuf,
+}
+
+impl FaviconCache {
+    /// Cache in the XDG cache directory
+    pub fn new() -> Option<FaviconCache> {
+        Some(FaviconCache {
+            dir: BaseDirs::new()?
+                .cache_dir()
+                .join("marcador")
+                .join("favicons"),
+        })
+    }
+
+    /// Cached icons by host, and the hosts that recently failed to download.
+    fn cached(&self) -> (HashMap<String, PathBuf>, Vec<String>) {
+        let mut icons = HashMap::new();
+        let mut failed = vec![];
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(_) => return (icons, failed),
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            let (host, extension) = match name.rsplit_once('.') {
+                Some((host, extension)) => (host.to_string(), extension),
+                None => continue,
+            };
+            if extension != MISSING {
+                icons.insert(host, path);
+            } else if entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                .is_some_and(|age| age < RETRY_AFTER)
+            {
+                failed.push(host);
+            }
+        }
+
+        (icons, failed)
+    }
+
+    /// Download the icon at `url` for `host`, remembering failures.
+    fn download(&self, client: &Client, host: &str, url: &Url) -> Option<PathBuf> {
+        let icon = client
+            .get(url.as_str())
+            .send()
+            .and_then(|response| response.error_for_status())
+            .ok()
+            .and_then(|response| {
+                let mime = response
+                    .headers()
+                    .get(CONTENT_TYPE)
+                    .and_then(|mime| mime.to_str().ok())
+                    .map(str::to_string);
+                let mut bytes = vec![];
+                response
+                    .take(MAX_ICON_SIZE + 1)
+                    .read_to_end(&mut bytes)
+                    .ok()?;
+                if bytes.len() as u64 > MAX_ICON_SIZE {
+                    return None;
+                }
+                let is_image = mime
+                    .as_deref()
+                    .is_none_or(|mime| mime.starts_with("image/"));
+                (is_image && !bytes.is_empty()).then_some((mime, bytes))
+            });
+
+        let missing = self.dir.join(format!("{}.{}", host, MISSING));
+        match icon {
+            Some((mime, bytes)) => {
+                let path = self
+                    .dir
+                    .join(format!("{}.{}", host, extension(mime.as_deref(), url)));
+                fs::write(&path, bytes).ok()?;
+                let _ = fs::remove_file(missing);
+                Some(path)
+            }
+            None => {
+                let _ = fs::write(missing, "");
+                None
+            }
+        }
+    }
+
+    /// Download the icons of `hosts`, `CONCURRENCY` at a time.
+    fn download_all(&self, hosts: &[(String, Url)]) -> Vec<(String, PathBuf)> {
+        let client = match Client::builder().timeout(TIMEOUT).build() {
+            Ok(client) => client,
+            Err(_) => return vec![],
+        };
+        if hosts.is_empty() || fs::create_dir_all(&self.dir).is_err() {
+            return vec![];
+        }
+        let next = AtomicUsize::new(0);
+
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..CONCURRENCY.min(hosts.len()))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut icons = vec![];
+                        while let Some((host, url)) =
+                            hosts.get(next.fetch_add(1, Ordering::Relaxed))
+                        {
+                            if let Some(path) = self.download(&client, host, url) {
+                                icons.push((host.clone(), path));
+                            }
+                        }
+                        icons
+                    })
+                })
+                .collect();
+
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap_or_default())
+                .collect()
+        })
+    }
+
+    /// Take the lock on the cache directory, none if another download holds it.
+    fn lock(&self) -> Option<Lock> {
+        fs::create_dir_all(&self.dir).ok()?;
+        let path = self.dir.join(LOCK);
+        let stale = fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .is_some_and(|age| age > STALE_LOCK);
+        if stale {
+            let _ = fs::remove_file(&path);
+        }
+        File::create_new(&path).ok()?;
+        Some(Lock(path))
+    }
+
+    /// Hosts of `bookmarks` without a cached icon, with the url of their icon.
+    fn missing(&self, bookmarks: &[Bookmark]) -> Vec<(String, Url)> {
+        let (icons, failed) = self.cached();
+        let mut missing: Vec<(String, Url)> = vec![];
+
+        for bookmark in bookmarks {
+            let url = match Url::parse(&bookmark.bookmark.url) {
+                Ok(url) => url,
+                Err(_) => continue,
+            };
+            let host = match host(&url) {
+                Some(host) => host,
+                None => continue,
+            };
+            if icons.contains_key(&host)
+                || failed.contains(&host)
+                || missing.iter().any(|(known, _)| *known == host)
+            {
+                continue;
+            }
+            let icon = bookmark
+                .metadata()
+                .and_then(|metadata| metadata.favicon)
+                .and_then(|icon| Url::parse(&icon).ok())
+                .or_else(|| match url.scheme() {
+                    "http" | "https" => url.join("/favicon.ico").ok(),
+                    _ => None,
+                });
+            if let Some(icon) = icon {
+                missing.push((host, icon));
+            }
+        }
+
+        missing
+    }
+
+    /// Cached icons of `bookmarks`.
+    pub fn icons(&self, bookmarks: &[Bookmark]) -> Vec<Option<PathBuf>> {
+        let (icons, _) = self.cached();
+
+        bookmarks
+            .iter()
+            .map(|bookmark| {
+                let url = Url::parse(&bookmark.bookmark.url).ok()?;
+                icons.get(&host(&url)?).cloned()
+            })
+            .collect()
+    }
+
+    /// Download the icons missing for `bookmarks` in a detached
+    /// `marcador fetch-icons`, so the menu isn't held up.
+    pub fn fetch_in_background(&self, bookmarks: &[Bookmark]) -> Result<(), String> {
+        let missing = self.missing(bookmarks);
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        let exe = env::current_exe().map_err(|err| format!("{}", err))?;
+        let mut child = Command::new(exe)
+            .arg("fetch-icons")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| format!("Failed to fetch icons: {}", err))?;
+        let mut stdin = child.stdin.take().ok_or("Failed to fetch icons")?;
+        for (host, icon) in missing {
+            writeln!(stdin, "{} {}", host, icon).map_err(|err| format!("{}", err))?;
+        }
+
+        Ok(())
+    }
+
+    /// Download the icons listed by `fetch_in_background` as `host url` lines,
+    /// unless another download is running.
+    pub fn fetch(&self, listed: impl BufRead) {
+        let _lock = match self.lock() {
+            Some(lock) => lock,
+            None => return,
+        };
+        let hosts: Vec<(String, Url)> = listed
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| {
+                let (host, icon) = line.split_once(' ')?;
+                Some((host.to_string(), Url::parse(icon).ok()?))
+            })
+            .collect();
+
+        self.download_all(&hosts);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::models::Bookmarks;
+
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    /// Serve a png at `/favicon.ico`, one too large to keep at `/large.png`
+    /// and 404 for anything else.
+    fn serve() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = String::new();
+                BufReader::new(&stream).read_line(&mut request).unwrap();
+                let response = match request.split_whitespace().nth(1) {
+                    Some("/favicon.ico") => {
+                        "200 OK\r\nContent-Type: image/png\r\nContent-Length: 4\r\n\r\nPNG!"
+                            .to_string()
+                    }
+                    Some("/large.png") => {
+                        let size = MAX_ICON_SIZE as usize + 1;
+                        format!(
+                            "200 OK\r\nContent-Type: image/png\r\nContent-Length: {}\r\n\r\n{}",
+                            size,
+                            "x".repeat(size)
+                        )
+                    }
+                    _ => "404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string(),
+                };
+                let _ = write!(stream, "HTTP/1.1 {}", response);
+            }
+        });
+
+        format!("http://{}", addr)
+    }
+
+    fn bookmark(url: &str, favicon: Option<&str>) -> Bookmark {
+        Bookmark::new(
+            &Bookmarks {
+                id: 1,
+                url: url.to_string(),
+                description: String::new(),
+                deleted_at: None,
+                metadata: favicon.map(|favicon| format!(r#"{{"favicon":"{}"}}"#, favicon)),
+            },
+            &[],
+        )
+    }
+
+    #[test]
+    fn icons_are_cached_per_host() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = FaviconCache {
+            dir: dir.path().join("favicons"),
+        };
+        let (found, gone) = (serve(), serve());
+        let bookmarks = vec![
+            bookmark(&format!("{}/a", found), None),
+            bookmark(&format!("{}/b", found), None),
+            bookmark(&format!("{}/c", gone), Some(&format!("{}/icon.png", gone))),
+            bookmark("mailto:me@example.com", None),
+        ];
+
+        assert_eq!(cache.icons(&bookmarks), vec![None; 4]);
+
+        let listed: String = cache
+            .missing(&bookmarks)
+            .iter()
+            .map(|(host, icon)| format!("{} {}\n", host, icon))
+            .collect();
+        cache.fetch(listed.as_bytes());
+        let icons = cache.icons(&bookmarks);
+        let found_host = found.trim_start_matches("http://");
+        let icon = cache.dir.join(format!("{}.png", found_host));
+        assert_eq!(
+            icons,
+            vec![Some(icon.clone()), Some(icon.clone()), None, None]
+        );
+        assert_eq!(fs::read(&icon).unwrap(), b"PNG!");
+
+        let gone_host = gone.trim_start_matches("http://");
+        assert!(cache.dir.join(format!("{}.missing", gone_host)).exists());
+        assert_eq!(cache.cached().1, vec![gone_host.to_string()]);
+        assert_eq!(cache.icons(&bookmarks), icons);
+        assert!(cache.missing(&bookmarks).is_empty());
+        assert!(!cache.dir.join(LOCK).exists());
+    }
+
+    #[test]
+    fn large_icons_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = FaviconCache {
+            dir: dir.path().join("favicons"),
+        };
+        let addr = serve();
+        let host = addr.trim_start_matches("http://").to_string();
+        let icon = Url::parse(&format!("{}/large.png", addr)).unwrap();
+
+        cache.fetch(format!("{} {}\n", host, icon).as_bytes());
+        assert!(cache.dir.join(format!("{}.missing", host)).exists());
+        assert!(cache.cached().0.is_empty());
+    }
+
+    #[test]
+    fn downloads_wait_for_the_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = FaviconCache {
+            dir: dir.path().join("favicons"),
+        };
+        let addr = serve();
+        let host = addr.trim_start_matches("http://").to_string();
+        let listed = format!("{} {}/favicon.ico\n", host, addr);
+
+        let lock = cache.lock().unwrap();
+        cache.fetch(listed.as_bytes());
+        assert!(cache.cached().0.is_empty());
+
+        drop(lock);
+        cache.fetch(listed.as_bytes());
+        assert_eq!(cache.cached().0.len(), 1);
+    }
+
+    #[test]
+    fn extensions_follow_the_content_type() {
+        let url = Url::parse("https://a.com/static/icon.SVG?v=2").unwrap();
+
+        assert_eq!(extension(Some("image/png; charset=binary"), &url), "png");
+        assert_eq!(extension(Some("image/vnd.microsoft.icon"), &url), "ico");
+        assert_eq!(extension(Some("application/octet-stream"), &url), "svg");
+        assert_eq!(
+            extension(None, &Url::parse("https://a.com/favicon").unwrap()),
+            "ico"
+        );
+    }
+}
@@ -0,0 +1,88 @@
+// Copyright 2024 João Freitas
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of
+// the GNU General Public License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS FOR
+// A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Ranking of bookmarks by how often and how recently they were opened, in
+//! the style of Firefox's frecency.
+
+use std::collections::HashMap;
+
+use crate::bookmark::{Bookmark, Visits};
+
+/// Number of most recent visits that are weighted
+const SAMPLE: usize = 10;
+
+const DAY: i64 = 24 * 60 * 60;
+
+/// Points given to a visit depending on its age
+fn weight(age: i64) -> i64 {
+    match age / DAY {
+        ..=4 => 100,
+        5..=14 => 70,
+        15..=31 => 50,
+        32..=90 => 30,
+        _ => 10,
+    }
+}
+
+/// Frecency of a bookmark visited `count` times, the latest at `visits`.
+/// The average weight of the sampled visits is scaled by the total count.
+pub fn frecency(visits: &[i64], count: i64, now: i64) -> i64 {
+    let mut recent = visits.to_vec();
+    recent.sort_unstable_by(|a, b| b.cmp(a));
+    recent.truncate(SAMPLE);
+
+    if recent.is_empty() {
+        return 0;
+    }
+
+    let points: i64 = recent.iter().map(|visit| weight(now - visit)).sum();
+    let sampled = recent.len() as i64;
+    (count * points + sampled - 1) / sampled
+}
+
+/// Order `bookmarks` by decreasing frecency, keeping the current order of
+/// bookmarks with the same score.
+pub fn sort_by_frecency(bookmarks: &mut [Bookmark], visits: &[Visits]) {
+    let scores: HashMap<i32, i64> = visits
+        .iter()
+        .map(|visits| (visits.bookmark_id, visits.frecency))
+        .collect();
+    bookmarks.sort_by_key(|bookmark| {
+        std::cmp::Reverse(scores.get(&bookmark.bookmark.id).copied().unwrap_or(0))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_000 * DAY;
+
+    #[test]
+    fn recent_visits_weigh_more() {
+        assert_eq!(frecency(&[], 0, NOW), 0);
+        assert_eq!(frecency(&[NOW], 1, NOW), 100);
+        assert_eq!(frecency(&[NOW - 10 * DAY], 1, NOW), 70);
+        assert_eq!(frecency(&[NOW - 365 * DAY], 1, NOW), 10);
+        assert_eq!(frecency(&[NOW, NOW - 60 * DAY], 2, NOW), 130);
+    }
+
+    #[test]
+    fn only_recent_visits_are_sampled() {
+        let mut visits = vec![NOW - 365 * DAY; 5];
+        visits.extend([NOW; SAMPLE]);
+
+        assert_eq!(frecency(&visits, 15, NOW), 1500);
+        assert!(frecency(&[NOW - 2 * DAY], 1, NOW) < frecency(&[NOW - 200 * DAY; 3], 20, NOW));
+    }
+}
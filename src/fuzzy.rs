@@ -0,0 +1,217 @@
+// Copyright 2024 João Freitas
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of
+// the GNU General Public License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS FOR
+// A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Fuzzy matching in the style of fzf: the characters of a pattern must
+//! appear in order, matches at word starts and in runs score higher and gaps
+//! between matched characters are penalised.
+
+use std::cmp::Reverse;
+
+use crate::bookmark::Bookmark;
+
+const SCORE_MATCH: i64 = 16;
+const SCORE_GAP_START: i64 = -3;
+const SCORE_GAP_EXTENSION: i64 = -1;
+/// Bonus for matching the first character of a word
+const BONUS_BOUNDARY: i64 = 8;
+/// Bonus for matching an uppercase character following a lowercase one
+const BONUS_CAMEL: i64 = 7;
+/// Minimum bonus for characters matched right after another match
+const BONUS_CONSECUTIVE: i64 = 4;
+/// The bonus of the first character of the pattern counts this many times
+const BONUS_FIRST_CHAR_MULTIPLIER: i64 = 2;
+
+fn lowercase(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// Bonus for matching the character of `text` at `index`.
+fn bonus(text: &[char], index: usize) -> i64 {
+    let c = text[index];
+    match index.checked_sub(1).map(|prev| text[prev]) {
+        None => BONUS_BOUNDARY,
+        Some(prev) if !prev.is_alphanumeric() && c.is_alphanumeric() => BONUS_BOUNDARY,
+        Some(prev) if prev.is_lowercase() && c.is_uppercase() => BONUS_CAMEL,
+        _ => 0,
+    }
+}
+
+/// Score of `pattern` in `text`, ignoring case, none if it doesn't match.
+///
+/// The shortest window ending at the first full match is scored, like fzf's
+/// first algorithm.
+pub fn score(pattern: &str, text: &str) -> Option<i64> {
+    let pattern: Vec<char> = pattern.chars().map(lowercase).collect();
+    if pattern.is_empty() {
+        return Some(0);
+    }
+    let text: Vec<char> = text.chars().collect();
+    let lower: Vec<char> = text.iter().copied().map(lowercase).collect();
+
+    let mut matched = 0;
+    let end = lower.iter().position(|c| {
+        if *c == pattern[matched] {
+            matched += 1;
+        }
+        matched == pattern.len()
+    })?;
+
+    let mut remaining = pattern.len();
+    let start = (0..=end).rev().find(|index| {
+        if lower[*index] == pattern[remaining - 1] {
+            remaining -= 1;
+        }
+        remaining == 0
+    })?;
+
+    let mut score = 0;
+    let mut matched = 0;
+    let mut in_gap = false;
+    let mut run_bonus = None;
+    for (index, c) in lower.iter().enumerate().take(end + 1).skip(start) {
+        if matched < pattern.len() && *c == pattern[matched] {
+            let mut bonus = bonus(&text, index);
+            match run_bonus {
+                Some(first) => bonus = bonus.max(first).max(BONUS_CONSECUTIVE),
+                None => run_bonus = Some(bonus),
+            }
+            if matched == 0 {
+                bonus *= BONUS_FIRST_CHAR_MULTIPLIER;
+            }
+            score += SCORE_MATCH + bonus;
+            matched += 1;
+            in_gap = false;
+        } else {
+            score += if in_gap {
+                SCORE_GAP_EXTENSION
+            } else {
+                SCORE_GAP_START
+            };
+            in_gap = true;
+            run_bonus = None;
+        }
+    }
+
+    Some(score)
+}
+
+/// `url` without its scheme, which nearly every url shares.
+fn strip_scheme(url: &str) -> &str {
+    match url.split_once("://") {
+        Some((scheme, rest)) if !scheme.contains(['/', '?', '#']) => rest,
+        _ => url,
+    }
+}
+
+/// Score of `bookmark` for `query`. Every word of the query must match the
+/// description, the tags or the url without its scheme, the best of which
+/// counts.
+pub fn bookmark_score(query: &str, bookmark: &Bookmark) -> Option<i64> {
+    let tags = bookmark
+        .tags
+        .iter()
+        .map(|tag| tag.tag.as_str())
+        .collect::<Vec<&str>>()
+        .join(" ");
+    let fields = [
+        bookmark.bookmark.description.as_str(),
+        tags.as_str(),
+        strip_scheme(&bookmark.bookmark.url),
+    ];
+
+    query
+        .split_whitespace()
+        .map(|word| fields.iter().filter_map(|field| score(word, field)).max())
+        .sum()
+}
+
+/// The bookmarks matching `query`, best matches first. Bookmarks with the
+/// same score keep their order.
+pub fn rank(query: &str, bookmarks: Vec<Bookmark>) -> Vec<Bookmark> {
+    let mut scored: Vec<(i64, Bookmark)> = bookmarks
+        .into_iter()
+        .filter_map(|bookmark| Some((bookmark_score(query, &bookmark)?, bookmark)))
+        .collect();
+    scored.sort_by_key(|(score, _)| Reverse(*score));
+    scored.into_iter().map(|(_, bookmark)| bookmark).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::models::{Bookmarks, Tags};
+
+    fn bookmark(id: i32, description: &str, url: &str, tags: &[&str]) -> Bookmark {
+        Bookmark::new(
+            &Bookmarks {
+                id,
+                url: url.to_string(),
+                description: description.to_string(),
+                deleted_at: None,
+                metadata: None,
+            },
+            &tags
+                .iter()
+                .enumerate()
+                .map(|(id, tag)| Tags {
+                    id: id as i32,
+                    tag: tag.to_string(),
+                })
+                .collect::<Vec<Tags>>(),
+        )
+    }
+
+    #[test]
+    fn characters_must_appear_in_order() {
+        assert!(score("abc", "a_b_c").is_some());
+        assert!(score("ABC", "xaxbxc").is_some());
+        assert_eq!(score("abc", "acb"), None);
+        assert_eq!(score("", "anything"), Some(0));
+        assert_eq!(score("a", ""), None);
+    }
+
+    #[test]
+    fn word_starts_and_runs_score_higher() {
+        assert!(score("rust", "rust book") > score("rust", "r u s t"));
+        assert!(score("doc", "docs.rs") > score("doc", "undocumented"));
+        assert!(score("rl", "rust-lang") > score("rl", "curling"));
+        assert!(score("fb", "FooBar") > score("fb", "foobar"));
+        // The shortest window is scored, not the first occurrence of `r`
+        assert_eq!(score("rs", "r____rs"), score("rs", "rs"));
+    }
+
+    #[test]
+    fn bookmarks_are_ranked() {
+        let bookmarks = vec![
+            bookmark(1, "Pie crust recipes", "https://example.com/crust", &[]),
+            bookmark(2, "Rust", "https://www.rust-lang.org", &["lang"]),
+            bookmark(3, "Python", "https://python.org", &["lang"]),
+            bookmark(4, "Docs", "https://docs.rs", &["rust", "docs"]),
+        ];
+        let ids = |bookmarks: Vec<Bookmark>| -> Vec<i32> {
+            bookmarks
+                .iter()
+                .map(|bookmark| bookmark.bookmark.id)
+                .collect()
+        };
+
+        assert_eq!(ids(rank("rust", bookmarks.clone())), vec![2, 4, 1]);
+        assert_eq!(ids(rank("rust lang", bookmarks.clone())), vec![2]);
+        assert_eq!(ids(rank("lang", bookmarks.clone())), vec![2, 3]);
+        assert_eq!(ids(rank("xyz", bookmarks.clone())), Vec::<i32>::new());
+        assert_eq!(ids(rank("hp", bookmarks.clone())), Vec::<i32>::new());
+        assert_eq!(ids(rank("st", bookmarks.clone())), vec![1, 2, 4]);
+        assert_eq!(ids(rank("", bookmarks)), vec![1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,393 @@
+// Copyright 2024 João Freitas
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of
+// the GNU General Public License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS FOR
+// A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Menus shown by an external launcher: rofi, dmenu, wofi, fuzzel, bemenu or
+//! fzf.
+//!
+//! Rows are Pango markup, launchers that don't render it show the plain text.
+//! Only rofi and fzf report custom keys, the others leave picking an action to
+//! a second menu.
+
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+
+use crate::config::LauncherKind;
+use crate::rofi;
+use crate::rofi::pango::strip;
+
+/// Menu to show
+#[derive(Default)]
+pub struct Menu<'a> {
+    /// Pango markup of each row
+    pub rows: &'a [String],
+    pub prompt: &'a str,
+    /// Pango markup shown above the rows
+    pub message: Option<String>,
+    /// Keys closing the menu besides Return, in rofi syntax like `Alt+n`
+    pub keys: &'a [String],
+    /// Whether several rows can be picked
+    pub multi: bool,
+    /// Whether text matching no row can be entered
+    pub input: bool,
+    /// Path of the icon of each row
+    pub icons: &'a [Option<String>],
+}
+
+/// What was picked in a menu
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selection {
+    /// Index in `Menu::keys` of the key that closed the menu, none for Return
+    pub key: Option<usize>,
+    /// Indices of the picked rows
+    pub rows: Vec<usize>,
+    /// Plain text of the first picked row, or the text entered
+    pub text: String,
+}
+
+pub trait Launcher {
+    /// Whether `Menu::keys` are reported
+    fn supports_keys(&self) -> bool;
+    /// Key that picks several rows, none if only one can be picked
+    fn multi_select_key(&self) -> Option<&'static str>;
+    /// Show `menu`, none if it was dismissed.
+    fn show(&self, menu: &Menu) -> Result<Option<Selection>, String>;
+}
+
+pub fn launcher(kind: LauncherKind) -> Box<dyn Launcher> {
+    match kind {
+        LauncherKind::Rofi => Box::new(Rofi),
+        LauncherKind::Fzf => Box::new(Fzf),
+        kind => Box::new(Dmenu(kind)),
+    }
+}
+
+/// Indices of the rows whose plain text is `text`, the first one only.
+fn find_row(rows: &[String], text: &str) -> Vec<usize> {
+    rows.iter()
+        .position(|row| strip(row) == text)
+        .into_iter()
+        .collect()
+}
+
+pub struct Rofi;
+
+impl Launcher for Rofi {
+    fn supports_keys(&self) -> bool {
+        true
+    }
+
+    fn multi_select_key(&self) -> Option<&'static str> {
+        Some("Shift+Return")
+    }
+
+    fn show(&self, menu: &Menu) -> Result<Option<Selection>, String> {
+        let mut window = rofi::Rofi::new(menu.rows);
+        window.pango().prompt(menu.prompt);
+        if let Some(message) = &menu.message {
+            window.message(message.clone());
+        }
+        if menu.multi {
+            window.multi_select();
+        }
+        for (id, key) in menu.keys.iter().enumerate() {
+            window.kb_custom(id as u32 + 1, key);
+        }
+        if menu.icons.iter().any(Option::is_some) {
+            window.show_icons();
+            for (index, icon) in menu.icons.iter().enumerate() {
+                if let Some(icon) = icon {
+                    window.row_option(index, "icon", icon.clone());
+                }
+            }
+        }
+
+        let key = |code: i32| usize::try_from(code - 10).ok();
+        if menu.input {
+            let (code, text) = match window.return_format(rofi::Format::StrippedText).run() {
+                Ok((code, Some(text))) => (code, text),
+                _ => return Ok(None),
+            };
+            return Ok(Some(Selection {
+                key: key(code),
+                rows: find_row(menu.rows, &text),
+                text,
+            }));
+        }
+
+        let (code, rows) = match window.run_indices() {
+            Ok(selection) => selection,
+            Err(_) => return Ok(None),
+        };
+        let text = rows
+            .first()
+            .map(|index| strip(&menu.rows[*index]))
+            .unwrap_or_default();
+        Ok(Some(Selection {
+            key: key(code),
+            rows,
+            text,
+        }))
+    }
+}
+
+/// Launchers reading rows from stdin and printing the picked one, without
+/// custom keys
+pub struct Dmenu(LauncherKind);
+
+/// Plain text of `rows`, numbering repeated rows so that the text printed by
+/// the launcher identifies a single row.
+fn unique_rows(rows: &[String]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    rows.iter()
+        .map(|row| {
+            let text = strip(row);
+            let mut unique = text.clone();
+            let mut count = 1;
+            while !seen.insert(unique.clone()) {
+                count += 1;
+                unique = format!("{} ({})", text, count);
+            }
+            unique
+        })
+        .collect()
+}
+
+impl Dmenu {
+    fn command(&self, prompt: &str) -> Command {
+        let (program, args): (&str, &[&str]) = match self.0 {
+            LauncherKind::Wofi => ("wofi", &["--dmenu", "-i", "--prompt"]),
+            LauncherKind::Fuzzel => ("fuzzel", &["--dmenu", "--prompt"]),
+            LauncherKind::Bemenu => ("bemenu", &["-i", "-l", "20", "-p"]),
+            _ => ("dmenu", &["-i", "-l", "20", "-p"]),
+        };
+        let mut command = Command::new(program);
+        command.args(args).arg(prompt);
+        command
+    }
+}
+
+impl Launcher for Dmenu {
+    fn supports_keys(&self) -> bool {
+        false
+    }
+
+    fn multi_select_key(&self) -> Option<&'static str> {
+        None
+    }
+
+    fn show(&self, menu: &Menu) -> Result<Option<Selection>, String> {
+        let rows = unique_rows(menu.rows);
+        let output = run(self.command(menu.prompt), &rows.join("\n"))?;
+
+        Ok(output
+            .filter(|text| !text.is_empty())
+            .map(|text| Selection {
+                key: None,
+                rows: rows
+                    .iter()
+                    .position(|row| *row == text)
+                    .into_iter()
+                    .collect(),
+                text,
+            }))
+    }
+}
+
+/// Terminal launcher, reporting keys with `--expect`
+pub struct Fzf;
+
+/// Name fzf gives to rofi key `key`, like `alt-n` for `Alt+n`.
+fn fzf_key(key: &str) -> String {
+    key.split('+')
+        .map(|part| match part.to_lowercase().as_str() {
+            "control" => "ctrl".to_string(),
+            "backspace" => "bspace".to_string(),
+            "return" => "enter".to_string(),
+            "delete" => "del".to_string(),
+            part => part.to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join("-")
+}
+
+/// Selection in the output of fzf run with `--print-query`, and `--expect`
+/// when `keys` isn't empty. Rows are prefixed with their index and a tab.
+fn parse_fzf(output: &str, keys: &[String]) -> Option<Selection> {
+    let mut lines = output.lines();
+    let query = lines.next()?.to_string();
+    let key = if keys.is_empty() {
+        None
+    } else {
+        let pressed = lines.next()?;
+        keys.iter().position(|key| fzf_key(key) == pressed)
+    };
+
+    let mut rows = vec![];
+    let mut text = None;
+    for line in lines {
+        if let Some((index, row)) = line.split_once('\t') {
+            rows.extend(index.parse::<usize>().ok());
+            text.get_or_insert_with(|| row.to_string());
+        }
+    }
+
+    Some(Selection {
+        key,
+        rows,
+        text: text.unwrap_or(query),
+    })
+}
+
+impl Launcher for Fzf {
+    fn supports_keys(&self) -> bool {
+        true
+    }
+
+    fn multi_select_key(&self) -> Option<&'static str> {
+        Some("Tab")
+    }
+
+    fn show(&self, menu: &Menu) -> Result<Option<Selection>, String> {
+        let rows: Vec<String> = menu
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(index, row)| format!("{}\t{}", index, strip(row).replace('\t', " ")))
+            .collect();
+
+        let mut command = Command::new("fzf");
+        command
+            .args(["--print-query", "--delimiter", "\t", "--with-nth", "2.."])
+            .arg("--prompt")
+            .arg(format!("{}> ", menu.prompt));
+        if let Some(message) = &menu.message {
+            command.arg("--header").arg(strip(message));
+        }
+        if menu.multi {
+            command.arg("--multi");
+        }
+        if !menu.keys.is_empty() {
+            let keys: Vec<String> = menu.keys.iter().map(|key| fzf_key(key)).collect();
+            command.arg("--expect").arg(keys.join(","));
+        }
+
+        let output = run(command, &rows.join("\n"))?;
+        Ok(output
+            .and_then(|output| parse_fzf(&output, menu.keys))
+            .filter(|selection| {
+                !selection.rows.is_empty() || (menu.input && !selection.text.is_empty())
+            }))
+    }
+}
+
+/// Run `command` with `input` on stdin, returning its output unless it was
+/// dismissed. Exit code 1 is dmenu's escape and fzf's no match.
+fn run(mut command: Command, input: &str) -> Result<Option<String>, String> {
+    let program = command.get_program().to_string_lossy().to_string();
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("Failed to run {}: {}", program, err))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(input.as_bytes())
+            .map_err(|err| format!("{}", err))?;
+    }
+    let mut output = String::new();
+    if let Some(mut stdout) = child.stdout.take() {
+        stdout
+            .read_to_string(&mut output)
+            .map_err(|err| format!("{}", err))?;
+    }
+    let status = child.wait().map_err(|err| format!("{}", err))?;
+
+    match status.code() {
+        Some(0) => Ok(Some(output.trim_end_matches('\n').to_string())),
+        // fzf prints the query when nothing matched it
+        Some(1) if program == "fzf" => Ok(Some(output)),
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markup_is_stripped() {
+        assert_eq!(
+            strip("<span weight='bold'>Rust &amp; &lt;Cargo&gt;</span>  #a"),
+            "Rust & <Cargo>  #a"
+        );
+        assert_eq!(
+            strip("a &unknown; & b <unclosed"),
+            "a &unknown; & b <unclosed"
+        );
+    }
+
+    #[test]
+    fn repeated_rows_are_numbered() {
+        let rows: Vec<String> = ["<b>Rust</b>  #a", "Rust  #a", "Rust  #a (2)", "Docs"]
+            .iter()
+            .map(|row| row.to_string())
+            .collect();
+        let unique = unique_rows(&rows);
+
+        assert_eq!(
+            unique,
+            vec!["Rust  #a", "Rust  #a (2)", "Rust  #a (2) (2)", "Docs"]
+        );
+        assert_eq!(unique.iter().position(|row| row == "Rust  #a (2)"), Some(1));
+    }
+
+    #[test]
+    fn fzf_keys_use_fzf_names() {
+        assert_eq!(fzf_key("Alt+n"), "alt-n");
+        assert_eq!(fzf_key("Alt+BackSpace"), "alt-bspace");
+        assert_eq!(fzf_key("Control+Return"), "ctrl-enter");
+    }
+
+    #[test]
+    fn fzf_output_is_parsed() {
+        let keys = vec!["Alt+n".to_string(), "Alt+d".to_string()];
+
+        assert_eq!(
+            parse_fzf("ru\nalt-d\n2\tRust  #lang\n5\tRuby\n", &keys),
+            Some(Selection {
+                key: Some(1),
+                rows: vec![2, 5],
+                text: "Rust  #lang".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_fzf("https://a.com\n\n", &keys),
+            Some(Selection {
+                key: None,
+                rows: vec![],
+                text: "https://a.com".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_fzf("web docs\n", &[]),
+            Some(Selection {
+                key: None,
+                rows: vec![],
+                text: "web docs".to_string(),
+            })
+        );
+        assert_eq!(parse_fzf("", &keys), None);
+    }
+}
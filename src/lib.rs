@@ -11,25 +11,51 @@
 // You should have received a copy of the GNU General Public License along with this
 // program. If not, see <https://www.gnu.org/licenses/>.
 
+pub mod archive;
 pub mod bookmark;
 pub mod bookmark_proxy;
+pub mod check;
+pub mod clipboard;
 pub mod config;
+pub mod editor;
+pub mod favicon;
+pub mod frecency;
+pub mod fuzzy;
+pub mod launcher;
 pub mod local_proxy;
+pub mod metadata;
 pub mod models;
+pub mod normalise;
+pub mod opener;
 pub mod remote_proxy;
 pub mod rofi;
 pub mod rofi_interface;
+pub mod rofi_script;
 pub mod schema;
 pub mod server;
+pub mod tui;
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use itertools::Itertools;
+use serde::Serialize;
 
-use bookmark_proxy::edit_bookmark;
+use std::fmt::Display;
+use std::io::{self, BufRead, Write};
+
+use bookmark::{LinkCheck, NewBookmark};
 use bookmark_proxy::BookmarkProxy;
 use config::Config;
+use editor::{add_bookmarks, edit_bookmarks, EditableBookmark};
+use favicon::FaviconCache;
+use frecency::sort_by_frecency;
 use local_proxy::LocalProxy;
+use metadata::PageMetadata;
+use normalise::{dedupe, duplicates};
+use opener::{open_archived, open_bookmark};
 use remote_proxy::RemoteProxy;
 use rofi_interface::command_rofi;
+use rofi_script::command_rofi_script;
+use tui::command_tui;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -40,66 +66,635 @@ pub struct Cli {
     /// Bookmark batabase path
     #[arg(long)]
     db: Option<String>,
+    /// Output format
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
     #[command(subcommand)]
     command: Commands,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SortOrder {
+    /// Oldest bookmarks first
+    Id,
+    /// Most frequently and recently opened bookmarks first
+    Frecency,
+    /// Best matches of the query first
+    Match,
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Rofi interface
-    Rofi,
+    Rofi {
+        /// Pick a tag first, then one of its bookmarks
+        #[arg(long, short)]
+        tags: bool,
+    },
+    /// Terminal interface
+    Tui,
+    /// Rofi script mode, run as `rofi -modi 'marcador:marcador rofi-script' -show marcador`
+    RofiScript {
+        /// Row picked in rofi
+        #[arg(allow_hyphen_values = true)]
+        selection: Option<String>,
+    },
     /// Add a new bookmark
     Add {
         /// Bookmark url
         url: String,
-        /// Bookmark description
-        description: String,
+        /// Bookmark description, defaults to the page title
+        description: Option<String>,
         /// List of bookmark tags
         tags: Vec<String>,
+        /// Bookmark description, every positional argument after the url is then a tag
+        #[arg(long = "description", short = 'd', id = "description_flag")]
+        description_flag: Option<String>,
+        /// Bookmark tag, can be repeated
+        #[arg(long = "tag", short = 't')]
+        tag: Vec<String>,
+        /// Complete the bookmark in $EDITOR before adding it
+        #[arg(long, short)]
+        edit: bool,
     },
     /// List bookmarks
-    List,
-    /// Delete bookmark by id
+    List {
+        /// Fuzzy query the listed bookmarks must match
+        query: Vec<String>,
+        /// Order of the listed bookmarks, best matches first with a query and oldest otherwise
+        #[arg(long, value_enum)]
+        sort: Option<SortOrder>,
+    },
+    /// Move bookmark to the trash by id
     Delete { index: i32 },
-    /// Edit bookmark by id
-    Edit { index: i32 },
+    /// Manage deleted bookmarks
+    Trash {
+        #[command(subcommand)]
+        command: TrashCommands,
+    },
+    /// Edit bookmarks by id or query in a single editor session
+    Edit {
+        indices: Vec<i32>,
+        /// Also edit the bookmarks matching this query
+        #[arg(long, short)]
+        query: Option<String>,
+    },
+    /// Show the changes made to a bookmark
+    History { index: i32 },
+    /// Restore a bookmark to an earlier revision
+    Revert { index: i32, rev: i32 },
+    /// Reverse the most recent change
+    Undo,
+    /// Merge bookmarks that point to the same page
+    Dedupe {
+        /// Only list the duplicates
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Request bookmark urls and list the broken ones
+    Check {
+        /// Bookmarks to check, all of them by default
+        indices: Vec<i32>,
+        /// List the bookmarks found broken by the last check without checking again
+        #[arg(long, short)]
+        broken: bool,
+        /// Add this tag to broken bookmarks
+        #[arg(long, short)]
+        tag: Option<String>,
+        /// Update urls that moved permanently without asking
+        #[arg(long, short)]
+        yes: bool,
+        /// Seconds to wait for each url
+        #[arg(long)]
+        timeout: Option<u64>,
+        /// Number of urls requested at the same time
+        #[arg(long)]
+        concurrency: Option<usize>,
+    },
+    /// Save offline copies of bookmarked pages
+    Archive {
+        /// Bookmarks to archive, all of them by default
+        indices: Vec<i32>,
+        /// List the stored copies instead
+        #[arg(long, short)]
+        list: bool,
+    },
+    /// Find bookmarks by description, url, tags or page text
+    Search {
+        #[arg(required = true)]
+        query: Vec<String>,
+    },
+    /// Download bookmarked pages and index their text for searching
+    Index {
+        /// Bookmarks to index, all of them by default
+        indices: Vec<i32>,
+    },
+    /// Open a bookmark by id, or the most visited one matching a query
+    Open {
+        #[arg(required = true)]
+        target: Vec<String>,
+        /// Open the latest offline copy instead of the page
+        #[arg(long, short)]
+        archived: bool,
+    },
+    /// Fuzzy find the bookmark best matching a query, read from stdin if not given
+    Pick {
+        query: Vec<String>,
+        /// Open the bookmark instead of printing it
+        #[arg(long, short)]
+        open: bool,
+    },
+    /// Download the favicons listed on stdin, run by the rofi menus
+    #[command(hide = true)]
+    FetchIcons,
+}
+
+#[derive(Subcommand)]
+enum TrashCommands {
+    /// List deleted bookmarks
+    List,
+    /// Restore deleted bookmark by id
+    Restore { index: i32 },
+    /// Permanently remove all deleted bookmarks
+    Empty,
 }
 
-fn get_proxy(host: Option<String>, db: Option<String>) -> Result<Box<dyn BookmarkProxy>, String> {
-    if let Some(db) = db {
-        Ok(Box::new(LocalProxy::new(&db)))
-    } else if let Some(host) = host {
-        Ok(Box::new(RemoteProxy::new(&host)))
+fn get_proxy(config: &Config) -> Result<Box<dyn BookmarkProxy>, String> {
+    if let Some(db) = &config.db {
+        let mut proxy = LocalProxy::with_tracking_params(db, config.get_tracking_params());
+        proxy.set_trash_retention(config.trash_retention_days)?;
+        Ok(Box::new(proxy))
+    } else if let Some(host) = &config.host {
+        Ok(Box::new(RemoteProxy::new(host)))
     } else {
         Err("You must provide either a --host or --db flag".to_string())
     }
 }
 
+/// Description and tags of `add`. With `--description` the positional
+/// description is the first tag.
+fn add_fields(
+    description: Option<String>,
+    tags: Vec<String>,
+    description_flag: Option<String>,
+    tag: Vec<String>,
+) -> (Option<String>, Vec<String>) {
+    match description_flag {
+        Some(flag) => (
+            Some(flag),
+            description.into_iter().chain(tags).chain(tag).collect(),
+        ),
+        None => (description, tags.into_iter().chain(tag).collect()),
+    }
+}
+
+/// Metadata of the page at `url`, unless fetching is disabled in `config`.
+fn page_metadata(config: &Config, url: &str) -> Option<PageMetadata> {
+    if !config.get_fetch_metadata() {
+        return None;
+    }
+    metadata::fetch(url)
+        .map_err(|err| eprintln!("{}", err))
+        .ok()
+}
+
+/// Bookmarks with the given ids followed by those matching `query`, each once.
+fn select_bookmarks(
+    proxy: &dyn BookmarkProxy,
+    ids: &[i32],
+    query: Option<&str>,
+) -> Result<Vec<bookmark::Bookmark>, String> {
+    let mut bookmarks = ids
+        .iter()
+        .unique()
+        .map(|id| proxy.bookmark(*id))
+        .collect::<Result<Vec<bookmark::Bookmark>, String>>()?;
+
+    if let Some(query) = query {
+        for bookmark in proxy.bookmarks()? {
+            if bookmark.matches(query) && !ids.contains(&bookmark.bookmark.id) {
+                bookmarks.push(bookmark);
+            }
+        }
+    }
+
+    Ok(bookmarks)
+}
+
+fn print_item<T: Serialize + Display>(item: &T, format: OutputFormat) -> Result<(), String> {
+    match format {
+        OutputFormat::Text => println!("{}", item),
+        OutputFormat::Json => println!("{}", to_json(item)?),
+    }
+    Ok(())
+}
+
+fn print_list<T: Serialize + Display>(items: &[T], format: OutputFormat) -> Result<(), String> {
+    match format {
+        OutputFormat::Text => {
+            for item in items {
+                println!("{}\n", item);
+            }
+        }
+        OutputFormat::Json => println!("{}", to_json(&items)?),
+    }
+    Ok(())
+}
+
+/// Ask `question` on the terminal and read a line of answer.
+fn prompt(question: &str) -> Result<String, String> {
+    eprint!("{} ", question);
+    io::stderr().flush().map_err(|err| format!("{}", err))?;
+
+    let mut answer = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .map_err(|err| format!("{}", err))?;
+    Ok(answer.trim().to_string())
+}
+
+/// Ask a yes or no question on the terminal, defaulting to no.
+fn confirm(question: &str) -> Result<bool, String> {
+    let answer = prompt(&format!("{} [y/N]", question))?;
+    Ok(matches!(answer.as_str(), "y" | "Y" | "yes"))
+}
+
+/// Add `tag` to the bookmarks of `checks`.
+fn tag_checked(proxy: &dyn BookmarkProxy, checks: &[LinkCheck], tag: &str) -> Result<(), String> {
+    for check in checks {
+        let mut tags: Vec<String> = proxy
+            .bookmark(check.bookmark_id)?
+            .tags
+            .into_iter()
+            .map(|tag| tag.tag)
+            .collect();
+        if !tags.iter().any(|other| other == tag) {
+            tags.push(tag.to_string());
+            proxy.update_tags(check.bookmark_id, &tags)?;
+        }
+    }
+    Ok(())
+}
+
+/// Offer to replace the urls of bookmarks that moved permanently.
+fn update_moved(proxy: &dyn BookmarkProxy, checks: &[LinkCheck], yes: bool) -> Result<(), String> {
+    for check in checks {
+        if let Some(target) = check.moved_to() {
+            if yes || confirm(&format!("{} moved to {}, update?", check.url, target))? {
+                if let Err(err) = proxy.update_url(check.bookmark_id, target) {
+                    eprintln!("Failed to update bookmark {}: {}", check.bookmark_id, err);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Bookmark with id `target`, or the most frecent one matching `target` as a query.
+fn find_bookmark(proxy: &dyn BookmarkProxy, target: &str) -> Result<bookmark::Bookmark, String> {
+    if let Ok(id) = target.parse::<i32>() {
+        return proxy.bookmark(id);
+    }
+
+    let mut bookmarks: Vec<bookmark::Bookmark> = proxy
+        .bookmarks()?
+        .into_iter()
+        .filter(|bookmark| bookmark.matches(target))
+        .collect();
+    sort_by_frecency(&mut bookmarks, &proxy.visits()?);
+    bookmarks
+        .into_iter()
+        .next()
+        .ok_or(format!("No bookmark matches {}", target))
+}
+
+fn print_duplicates(
+    groups: &[Vec<bookmark::Bookmark>],
+    dry_run: bool,
+    format: OutputFormat,
+) -> Result<(), String> {
+    if format == OutputFormat::Json {
+        println!("{}", to_json(&groups)?);
+        return Ok(());
+    }
+
+    for group in groups {
+        let ids: Vec<String> = group[1..]
+            .iter()
+            .map(|bookmark| bookmark.bookmark.id.to_string())
+            .collect();
+        println!(
+            "{} {} into {}",
+            if dry_run { "Would merge" } else { "Merged" },
+            ids.join(", "),
+            group[0].bookmark.id
+        );
+    }
+    Ok(())
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, String> {
+    serde_json::to_string_pretty(value).map_err(|err| format!("{}", err))
+}
+
 pub fn marcador(cli: Cli) -> Result<(), String> {
     let mut config = Config::read().ok_or("Failed to read config".to_string())?;
 
     config.set_host(&cli.host);
     config.set_db(&cli.db);
 
-    let proxy = get_proxy(config.host, config.db)?;
+    if let Commands::FetchIcons = cli.command {
+        if let Some(cache) = FaviconCache::new() {
+            cache.fetch(io::stdin().lock());
+        }
+        return Ok(());
+    }
+
+    let proxy = get_proxy(&config)?;
     match cli.command {
-        Commands::Rofi => command_rofi(&*proxy),
+        Commands::Rofi { tags } => command_rofi(&*proxy, &config, tags),
+        Commands::Tui => command_tui(&*proxy, &config),
+        Commands::RofiScript { selection } => command_rofi_script(&*proxy, &config, selection),
         Commands::Add {
             url,
             description,
             tags,
-        } => proxy.add(&url, &description, tags),
-        Commands::List => {
-            for bookmark in proxy.bookmarks()? {
-                println!("{}\n", bookmark);
+            description_flag,
+            tag,
+            edit,
+        } => {
+            let (description, tags) = add_fields(description, tags, description_flag, tag);
+            let metadata = match description {
+                Some(_) => None,
+                None => page_metadata(&config, &url),
+            };
+            let description = description
+                .or_else(|| metadata.as_ref().and_then(PageMetadata::best_title))
+                .unwrap_or_default();
+
+            let bookmarks = if edit {
+                let template = EditableBookmark {
+                    id: None,
+                    url,
+                    description,
+                    tags,
+                };
+                add_bookmarks(&*proxy, &template, metadata.as_ref(), false)?
+            } else {
+                vec![proxy.add(&NewBookmark {
+                    url,
+                    description,
+                    tags,
+                    metadata,
+                })?]
+            };
+
+            for bookmark in &bookmarks {
+                match cli.format {
+                    OutputFormat::Text => println!("{}", bookmark.bookmark.id),
+                    OutputFormat::Json => println!("{}", to_json(bookmark)?),
+                }
             }
             Ok(())
         }
+        Commands::List { query, sort } => {
+            let query = query.join(" ");
+            let mut bookmarks: Vec<bookmark::Bookmark> = proxy
+                .bookmarks()?
+                .into_iter()
+                .filter(|bookmark| fuzzy::bookmark_score(&query, bookmark).is_some())
+                .collect();
+            let default = if query.is_empty() {
+                SortOrder::Id
+            } else {
+                SortOrder::Match
+            };
+            match sort.unwrap_or(default) {
+                SortOrder::Id => {}
+                SortOrder::Frecency => sort_by_frecency(&mut bookmarks, &proxy.visits()?),
+                SortOrder::Match => bookmarks = fuzzy::rank(&query, bookmarks),
+            }
+            print_list(&bookmarks, cli.format)
+        }
         Commands::Delete { index } => proxy.delete(index),
-        Commands::Edit { index } => {
-            edit_bookmark(&*proxy, index, None);
+        Commands::Trash { command } => match command {
+            TrashCommands::List => print_list(&proxy.trash()?, cli.format),
+            TrashCommands::Restore { index } => print_item(&proxy.restore(index)?, cli.format),
+            TrashCommands::Empty => {
+                let removed = proxy.empty_trash()?;
+                match cli.format {
+                    OutputFormat::Text => println!("Removed {} bookmarks", removed),
+                    OutputFormat::Json => {
+                        println!("{}", to_json(&serde_json::json!({ "removed": removed }))?)
+                    }
+                }
+                Ok(())
+            }
+        },
+        Commands::Edit { indices, query } => {
+            let bookmarks = select_bookmarks(&*proxy, &indices, query.as_deref())?;
+            if bookmarks.is_empty() {
+                return Err("No bookmarks to edit".to_string());
+            }
+            let changes = edit_bookmarks(&*proxy, &bookmarks, false)?;
+            let (updated, added, deleted) = (
+                changes.updated.len(),
+                changes.added.len(),
+                changes.deleted.len(),
+            );
+            match cli.format {
+                OutputFormat::Text => println!(
+                    "Updated {}, added {} and deleted {} bookmarks",
+                    updated, added, deleted
+                ),
+                OutputFormat::Json => println!(
+                    "{}",
+                    to_json(&serde_json::json!({
+                        "updated": updated,
+                        "added": added,
+                        "deleted": deleted,
+                    }))?
+                ),
+            }
             Ok(())
         }
+        Commands::History { index } => print_list(&proxy.history(index)?, cli.format),
+        Commands::Revert { index, rev } => print_item(&proxy.revert(index, rev)?, cli.format),
+        Commands::Undo => {
+            let revision = proxy.undo()?;
+            match cli.format {
+                OutputFormat::Text => println!(
+                    "Undid {} of bookmark {}",
+                    revision.action, revision.bookmark_id
+                ),
+                OutputFormat::Json => println!("{}", to_json(&revision)?),
+            }
+            Ok(())
+        }
+        Commands::Dedupe { dry_run } => {
+            let tracking_params = config.get_tracking_params();
+            let groups = if dry_run {
+                duplicates(&proxy.bookmarks()?, &tracking_params)
+            } else {
+                dedupe(&*proxy, &tracking_params)?
+            };
+            print_duplicates(&groups, dry_run, cli.format)
+        }
+        Commands::Check {
+            indices,
+            broken,
+            tag,
+            yes,
+            timeout,
+            concurrency,
+        } => {
+            let checks = if broken {
+                proxy
+                    .link_checks()?
+                    .into_iter()
+                    .filter(|check| indices.is_empty() || indices.contains(&check.bookmark_id))
+                    .filter(|check| check::is_checkable(&check.url))
+                    .collect()
+            } else {
+                let mut check_config = config.check.clone().unwrap_or_default();
+                check_config.set_timeout(&timeout);
+                check_config.set_concurrency(&concurrency);
+
+                let bookmarks = if indices.is_empty() {
+                    proxy.bookmarks()?
+                } else {
+                    select_bookmarks(&*proxy, &indices, None)?
+                };
+                check::check(&*proxy, &bookmarks, &check_config)?
+            };
+
+            let broken_checks: Vec<LinkCheck> = checks
+                .iter()
+                .filter(|check| check.is_broken())
+                .cloned()
+                .collect();
+            if let Some(tag) = &tag {
+                tag_checked(&*proxy, &broken_checks, tag)?;
+            }
+            print_list(&broken_checks, cli.format)?;
+            if cli.format == OutputFormat::Text {
+                println!(
+                    "{} of {} bookmarks are broken",
+                    broken_checks.len(),
+                    checks.len()
+                );
+            }
+
+            if broken {
+                Ok(())
+            } else {
+                update_moved(&*proxy, &checks, yes)
+            }
+        }
+        Commands::Archive { indices, list } => {
+            let bookmarks = if indices.is_empty() {
+                proxy.bookmarks()?
+            } else {
+                select_bookmarks(&*proxy, &indices, None)?
+            };
+
+            if list {
+                let snapshots = bookmarks
+                    .iter()
+                    .map(|bookmark| proxy.snapshots(bookmark.bookmark.id))
+                    .flatten_ok()
+                    .collect::<Result<Vec<_>, String>>()?;
+                return print_list(&snapshots, cli.format);
+            }
+
+            let bookmarks: Vec<_> = bookmarks
+                .into_iter()
+                .filter(|bookmark| check::is_checkable(&bookmark.bookmark.url))
+                .collect();
+            let mut snapshots = vec![];
+            for bookmark in &bookmarks {
+                match archive::snapshot_page(&bookmark.bookmark.url)
+                    .and_then(|content| proxy.store_snapshot(bookmark.bookmark.id, &content))
+                {
+                    Ok(snapshot) => snapshots.push(snapshot),
+                    Err(err) => eprintln!("{}", err),
+                }
+            }
+            print_list(&snapshots, cli.format)?;
+            if cli.format == OutputFormat::Text {
+                println!(
+                    "Archived {} of {} bookmarks",
+                    snapshots.len(),
+                    bookmarks.len()
+                );
+            }
+            Ok(())
+        }
+        Commands::Search { query } => print_list(&proxy.search(&query.join(" "))?, cli.format),
+        Commands::Index { indices } => {
+            let bookmarks = if indices.is_empty() {
+                proxy.bookmarks()?
+            } else {
+                select_bookmarks(&*proxy, &indices, None)?
+            };
+
+            let bookmarks: Vec<_> = bookmarks
+                .into_iter()
+                .filter(|bookmark| check::is_checkable(&bookmark.bookmark.url))
+                .collect();
+            let mut indexed = 0;
+            for bookmark in &bookmarks {
+                match archive::fetch_text(&bookmark.bookmark.url)
+                    .and_then(|text| proxy.set_page_text(bookmark.bookmark.id, &text))
+                {
+                    Ok(()) => indexed += 1,
+                    Err(err) => eprintln!("{}", err),
+                }
+            }
+            if cli.format == OutputFormat::Text {
+                println!("Indexed {} of {} bookmarks", indexed, bookmarks.len());
+            }
+            Ok(())
+        }
+        Commands::Open { target, archived } => {
+            let bookmark = find_bookmark(&*proxy, &target.join(" "))?;
+            let open_config = config.open.clone().unwrap_or_default();
+            if archived {
+                open_archived(&open_config, &*proxy, bookmark.bookmark.id)?;
+            } else {
+                open_bookmark(&open_config, &bookmark)?;
+            }
+            proxy.visit(bookmark.bookmark.id)
+        }
+        Commands::Pick { query, open } => {
+            let query = match query.join(" ") {
+                query if query.is_empty() => prompt("Query:")?,
+                query => query,
+            };
+            if query.trim().is_empty() {
+                return Err("Empty query".to_string());
+            }
+            let mut bookmarks = proxy.bookmarks()?;
+            sort_by_frecency(&mut bookmarks, &proxy.visits()?);
+            let bookmark = fuzzy::rank(&query, bookmarks)
+                .into_iter()
+                .next()
+                .ok_or(format!("No bookmark matches {}", query))?;
+            if open {
+                open_bookmark(&config.open.clone().unwrap_or_default(), &bookmark)?;
+                proxy.visit(bookmark.bookmark.id)
+            } else {
+                print_item(&bookmark, cli.format)
+            }
+        }
+        Commands::FetchIcons => unreachable!("Icons are fetched without a proxy"),
     }?;
 
     Ok(())
@@ -107,8 +702,45 @@ pub fn marcador(cli: Cli) -> Result<(), String> {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
     fn test1() {
         assert_eq!(1, 1);
     }
+
+    fn add_args(args: &[&str]) -> (Option<String>, Vec<String>) {
+        let cli = Cli::try_parse_from(["marcador", "add", "https://a.com"].iter().chain(args))
+            .unwrap_or_else(|err| panic!("{}", err));
+        match cli.command {
+            Commands::Add {
+                description,
+                tags,
+                description_flag,
+                tag,
+                ..
+            } => add_fields(description, tags, description_flag, tag),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn add_description_flag_takes_positional_tags() {
+        let strings =
+            |words: &[&str]| -> Vec<String> { words.iter().map(|word| word.to_string()).collect() };
+
+        assert_eq!(
+            add_args(&["-d", "Desc", "rust", "cli"]),
+            (Some("Desc".to_string()), strings(&["rust", "cli"]))
+        );
+        assert_eq!(
+            add_args(&["rust", "-d", "Desc", "-t", "web"]),
+            (Some("Desc".to_string()), strings(&["rust", "web"]))
+        );
+        assert_eq!(
+            add_args(&["Desc", "rust", "-t", "cli"]),
+            (Some("Desc".to_string()), strings(&["rust", "cli"]))
+        );
+        assert_eq!(add_args(&[]), (None, vec![]));
+    }
 }
@@ -12,14 +12,24 @@
 // program. If not, see <https://www.gnu.org/licenses/>.
 
 use diesel::prelude::*;
-use diesel::{delete, insert_into};
+use diesel::sql_types::{Integer, Text};
+use diesel::{delete, insert_into, sql_query};
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
 
 use dotenvy::dotenv;
 
-use crate::bookmark::Bookmark;
+use std::cmp::Reverse;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::archive::{page_text, Store};
+use crate::bookmark::{
+    Bookmark, LinkCheck, NewBookmark, Revision, SearchResult, Snapshot, TagCount, Visits,
+};
 use crate::bookmark_proxy::BookmarkProxy;
-use crate::models::{BookmarkTags, Bookmarks, Tags};
+use crate::frecency::frecency;
+use crate::fuzzy;
+use crate::models::{BookmarkTags, Bookmarks, LinkChecks, PageMatches, Revisions, Snapshots, Tags};
+use crate::normalise::{default_tracking_params, url_key};
 
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
 
@@ -29,18 +39,441 @@ pub fn establish_connection(url: &str) -> Result<SqliteConnection, String> {
     SqliteConnection::establish(url).map_err(|_| format!("Error connecting to {}", url))
 }
 
+/// Current time as a unix timestamp
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+fn to_revision(revision: Revisions) -> Result<Revision, String> {
+    Ok(Revision {
+        bookmark_id: revision.bookmark_id,
+        rev: revision.rev,
+        action: revision.action,
+        url: revision.url,
+        description: revision.description,
+        tags: serde_json::from_str(&revision.tags).map_err(|err| format!("{}", err))?,
+        created_at: revision.created_at,
+    })
+}
+
+fn to_link_check(check: LinkChecks) -> LinkCheck {
+    LinkCheck {
+        bookmark_id: check.bookmark_id,
+        url: check.url,
+        status: check.status,
+        redirect: check.redirect,
+        error: check.error,
+        checked_at: check.checked_at,
+    }
+}
+
+fn to_snapshot(snapshot: Snapshots) -> Snapshot {
+    Snapshot {
+        bookmark_id: snapshot.bookmark_id,
+        hash: snapshot.hash,
+        url: snapshot.url,
+        size: snapshot.size,
+        created_at: snapshot.created_at,
+    }
+}
+
+/// Full text query matching pages that contain every word of `query`, none
+/// if it has no words.
+fn fts_query(query: &str) -> Option<String> {
+    let words: Vec<String> = query
+        .split_whitespace()
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect();
+
+    if words.is_empty() {
+        None
+    } else {
+        Some(words.join(" "))
+    }
+}
+
+/// Map the number of rows touched by a statement on a single bookmark to a result.
+fn found(rows: usize) -> Result<(), String> {
+    if rows == 0 {
+        Err("Bookmark not found".to_string())
+    } else {
+        Ok(())
+    }
+}
+
 pub struct LocalProxy {
     path: String,
+    tracking_params: Vec<String>,
+    trash_retention: Option<i64>,
 }
 
 impl LocalProxy {
     pub fn new(path: &str) -> LocalProxy {
+        LocalProxy::with_tracking_params(path, default_tracking_params())
+    }
+
+    /// Open the database at `path`, stripping `tracking_params` from urls when
+    /// looking for duplicates.
+    pub fn with_tracking_params(path: &str, tracking_params: Vec<String>) -> LocalProxy {
         let mut connection = establish_connection(path).unwrap();
         connection.run_pending_migrations(MIGRATIONS).unwrap();
 
-        LocalProxy {
+        let proxy = LocalProxy {
             path: path.to_string(),
+            tracking_params,
+            trash_retention: None,
+        };
+        proxy.migrate_data().unwrap();
+        proxy
+    }
+
+    /// Bring stored data up to date: url keys when the tracking parameters
+    /// changed and, once, revisions of bookmarks added before history existed.
+    fn migrate_data(&self) -> Result<(), String> {
+        let params = self.tracking_params.join("\n");
+        if self.setting("tracking_params")?.as_ref() != Some(&params) {
+            self.update_url_keys()?;
+            self.set_setting("tracking_params", &params)?;
         }
+
+        if self.setting("revisions_recorded")?.is_none() {
+            self.record_missing_revisions()?;
+            self.set_setting("revisions_recorded", "1")?;
+        }
+
+        Ok(())
+    }
+
+    fn setting(&self, key: &str) -> Result<Option<String>, String> {
+        use crate::schema::settings::dsl as sdsl;
+
+        let conn = &mut establish_connection(&self.path)?;
+        sdsl::settings
+            .filter(sdsl::key.eq(key))
+            .select(sdsl::value)
+            .first(conn)
+            .optional()
+            .map_err(|err| format!("{:?}", err))
+    }
+
+    fn set_setting(&self, key: &str, value: &str) -> Result<(), String> {
+        use crate::schema::settings::dsl as sdsl;
+
+        let conn = &mut establish_connection(&self.path)?;
+        diesel::replace_into(sdsl::settings)
+            .values((sdsl::key.eq(key), sdsl::value.eq(value)))
+            .execute(conn)
+            .map_err(|err| format!("{:?}", err))?;
+
+        Ok(())
+    }
+
+    /// Purge bookmarks that have been in the trash for longer than `days`.
+    pub fn set_trash_retention(&mut self, days: Option<u64>) -> Result<(), String> {
+        self.trash_retention = days.map(|days| days as i64 * 24 * 60 * 60);
+        self.purge_expired()?;
+        Ok(())
+    }
+
+    fn purge_expired(&self) -> Result<usize, String> {
+        match self.trash_retention {
+            Some(retention) => self.purge(Some(now() - retention)),
+            None => Ok(0),
+        }
+    }
+
+    /// Permanently remove trashed bookmarks, only those deleted before `before` if given.
+    fn purge(&self, before: Option<i64>) -> Result<usize, String> {
+        use crate::schema::bookmarks::dsl as bdsl;
+
+        let conn = &mut establish_connection(&self.path)?;
+        let ids: Vec<i32> = bdsl::bookmarks
+            .filter(bdsl::deleted_at.is_not_null())
+            .filter(bdsl::deleted_at.lt(before.unwrap_or(i64::MAX)))
+            .select(bdsl::id)
+            .get_results(conn)
+            .map_err(|err| format!("{:?}", err))?;
+
+        self.remove(&ids)
+    }
+
+    /// Permanently remove bookmarks together with their tags, history, checks,
+    /// snapshots and visits.
+    fn remove(&self, ids: &[i32]) -> Result<usize, String> {
+        use crate::schema::bookmarks::dsl as bdsl;
+        use crate::schema::bookmarks_tags::dsl as btdsl;
+        use crate::schema::link_checks::dsl as ldsl;
+        use crate::schema::revisions::dsl as rdsl;
+        use crate::schema::visits::dsl as vdsl;
+
+        self.remove_snapshots(ids)?;
+
+        let conn = &mut establish_connection(&self.path)?;
+        delete(vdsl::visits.filter(vdsl::bookmark_id.eq_any(ids)))
+            .execute(conn)
+            .map_err(|err| format!("{}", err))?;
+        for id in ids {
+            sql_query("delete from page_text where rowid = ?")
+                .bind::<Integer, _>(id)
+                .execute(conn)
+                .map_err(|err| format!("{}", err))?;
+        }
+        delete(btdsl::bookmarks_tags.filter(btdsl::bookmark_id.eq_any(ids)))
+            .execute(conn)
+            .map_err(|err| format!("{}", err))?;
+        delete(ldsl::link_checks.filter(ldsl::bookmark_id.eq_any(ids)))
+            .execute(conn)
+            .map_err(|err| format!("{}", err))?;
+        delete(rdsl::revisions.filter(rdsl::bookmark_id.eq_any(ids)))
+            .execute(conn)
+            .map_err(|err| format!("{}", err))?;
+        delete(bdsl::bookmarks.filter(bdsl::id.eq_any(ids)))
+            .execute(conn)
+            .map_err(|_| "Failed to purge bookmarks".to_string())
+    }
+
+    /// Delete the snapshots of bookmarks `ids`, removing stored copies no
+    /// other bookmark refers to.
+    fn remove_snapshots(&self, ids: &[i32]) -> Result<(), String> {
+        use crate::schema::snapshots::dsl as sdsl;
+
+        let conn = &mut establish_connection(&self.path)?;
+        let hashes: Vec<String> = sdsl::snapshots
+            .filter(sdsl::bookmark_id.eq_any(ids))
+            .select(sdsl::hash)
+            .distinct()
+            .load(conn)
+            .map_err(|err| format!("{:?}", err))?;
+        delete(sdsl::snapshots.filter(sdsl::bookmark_id.eq_any(ids)))
+            .execute(conn)
+            .map_err(|err| format!("{}", err))?;
+
+        let archive = self.archive();
+        for hash in hashes {
+            let references: i64 = sdsl::snapshots
+                .filter(sdsl::hash.eq(&hash))
+                .count()
+                .get_result(conn)
+                .map_err(|err| format!("{:?}", err))?;
+            if references == 0 {
+                archive.remove(&hash)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn archive(&self) -> Store {
+        Store::for_db(&self.path)
+    }
+
+    fn url_key(&self, url: &str) -> String {
+        url_key(url, &self.tracking_params)
+    }
+
+    /// Recompute stored url keys that are missing or were computed with
+    /// different tracking parameters.
+    fn update_url_keys(&self) -> Result<(), String> {
+        use crate::schema::bookmarks::dsl as bdsl;
+
+        let conn = &mut establish_connection(&self.path)?;
+        let rows: Vec<(i32, String, String)> = bdsl::bookmarks
+            .select((bdsl::id, bdsl::url, bdsl::url_key))
+            .load(conn)
+            .map_err(|err| format!("{:?}", err))?;
+
+        for (id, url, key) in rows {
+            let new_key = self.url_key(&url);
+            if key != new_key {
+                diesel::update(bdsl::bookmarks)
+                    .filter(bdsl::id.eq(id))
+                    .set(bdsl::url_key.eq(new_key))
+                    .execute(conn)
+                    .map_err(|err| format!("{:?}", err))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fail if a bookmark other than `id` already points to the same page as `url`.
+    fn check_duplicate(&self, url: &str, id: Option<i32>) -> Result<(), String> {
+        use crate::schema::bookmarks::dsl as bdsl;
+
+        let conn = &mut establish_connection(&self.path)?;
+        let ids: Vec<i32> = bdsl::bookmarks
+            .filter(bdsl::url_key.eq(self.url_key(url)))
+            .filter(bdsl::deleted_at.is_null())
+            .select(bdsl::id)
+            .get_results(conn)
+            .map_err(|err| format!("{:?}", err))?;
+
+        if ids.iter().any(|other| Some(*other) != id) {
+            Err("Bookmark already exists".to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Load a bookmark whether or not it is in the trash.
+    fn load(&self, id: i32) -> Result<Bookmark, String> {
+        use crate::schema::bookmarks::dsl as bdsl;
+
+        let conn = &mut establish_connection(&self.path)?;
+        let bookmark = bdsl::bookmarks
+            .filter(bdsl::id.eq(id))
+            .select(Bookmarks::as_select())
+            .get_result(conn)
+            .map_err(|err| match err {
+                diesel::result::Error::NotFound => "Bookmark not found".to_string(),
+                err => format!("Failed to load bookmark: {:?}", err),
+            })?;
+
+        Ok(Bookmark::new(&bookmark, &self.get_tags(&bookmark)?))
+    }
+
+    fn revisions(&self, id: i32) -> Result<Vec<Revisions>, String> {
+        use crate::schema::revisions::dsl as rdsl;
+
+        let conn = &mut establish_connection(&self.path)?;
+        rdsl::revisions
+            .filter(rdsl::bookmark_id.eq(id))
+            .order(rdsl::rev.asc())
+            .select(Revisions::as_select())
+            .load(conn)
+            .map_err(|err| format!("{:?}", err))
+    }
+
+    /// Store the current state of a bookmark as a new revision. Updates that
+    /// leave the bookmark unchanged are not recorded.
+    fn record(&self, id: i32, action: &str) -> Result<(), String> {
+        use crate::schema::revisions::dsl as rdsl;
+
+        let bookmark = self.load(id)?;
+        let tags: Vec<String> = bookmark.tags.iter().map(|tag| tag.tag.clone()).collect();
+        let tags = serde_json::to_string(&tags).map_err(|err| format!("{}", err))?;
+
+        let last = self.revisions(id)?.pop();
+        if let Some(last) = &last {
+            let unchanged = last.url == bookmark.bookmark.url
+                && last.description == bookmark.bookmark.description
+                && last.tags == tags;
+            if unchanged && (action == "update" || action == "revert") {
+                return Ok(());
+            }
+        }
+
+        let conn = &mut establish_connection(&self.path)?;
+        insert_into(rdsl::revisions)
+            .values((
+                rdsl::bookmark_id.eq(id),
+                rdsl::rev.eq(last.map(|last| last.rev + 1).unwrap_or(1)),
+                rdsl::action.eq(action),
+                rdsl::url.eq(&bookmark.bookmark.url),
+                rdsl::description.eq(&bookmark.bookmark.description),
+                rdsl::tags.eq(tags),
+                rdsl::created_at.eq(now()),
+            ))
+            .execute(conn)
+            .map_err(|_| "Failed to record revision".to_string())?;
+
+        Ok(())
+    }
+
+    /// Give bookmarks created before revisions were tracked an initial revision.
+    fn record_missing_revisions(&self) -> Result<(), String> {
+        use crate::schema::bookmarks::dsl as bdsl;
+        use crate::schema::revisions::dsl as rdsl;
+
+        let conn = &mut establish_connection(&self.path)?;
+        let ids: Vec<i32> = bdsl::bookmarks
+            .filter(bdsl::id.ne_all(rdsl::revisions.select(rdsl::bookmark_id)))
+            .select(bdsl::id)
+            .load(conn)
+            .map_err(|err| format!("{:?}", err))?;
+
+        for id in ids {
+            self.record(id, "add")?;
+        }
+
+        Ok(())
+    }
+
+    fn set_deleted_at(&self, id: i32, deleted_at: Option<i64>) -> Result<(), String> {
+        use crate::schema::bookmarks::dsl as bdsl;
+
+        let conn = &mut establish_connection(&self.path)?;
+        let updated = diesel::update(bdsl::bookmarks)
+            .filter(bdsl::id.eq(id))
+            .filter(bdsl::deleted_at.is_null().eq(deleted_at.is_some()))
+            .set(bdsl::deleted_at.eq(deleted_at))
+            .execute(conn)
+            .map_err(|_| "Failed to update bookmark".to_string())?;
+
+        found(updated)
+    }
+
+    fn set_description(&self, id: i32, description: &str) -> Result<(), String> {
+        use crate::schema::bookmarks::dsl as bdsl;
+
+        let conn = &mut establish_connection(&self.path)?;
+        let updated = diesel::update(bdsl::bookmarks)
+            .filter(bdsl::id.eq(id))
+            .filter(bdsl::deleted_at.is_null())
+            .set(bdsl::description.eq(description))
+            .execute(conn)
+            .map_err(|_| "Failed to update description".to_string())?;
+
+        found(updated)
+    }
+
+    /// Change the url of a bookmark, forgetting the check of the old url.
+    fn set_url(&self, id: i32, url: &str) -> Result<(), String> {
+        use crate::schema::bookmarks::dsl as bdsl;
+        use crate::schema::link_checks::dsl as ldsl;
+
+        self.check_duplicate(url, Some(id))?;
+
+        let conn = &mut establish_connection(&self.path)?;
+        let updated = diesel::update(bdsl::bookmarks)
+            .filter(bdsl::id.eq(id))
+            .filter(bdsl::deleted_at.is_null())
+            .set((bdsl::url.eq(url), bdsl::url_key.eq(self.url_key(url))))
+            .execute(conn)
+            .map_err(|_| "Failed to update url".to_string())?;
+        found(updated)?;
+
+        delete(ldsl::link_checks.filter(ldsl::bookmark_id.eq(id)))
+            .execute(conn)
+            .map_err(|err| format!("{}", err))?;
+
+        Ok(())
+    }
+
+    fn set_tags(&self, id: i32, tags: &[String]) -> Result<(), String> {
+        use crate::schema::bookmarks_tags::dsl as btdsl;
+
+        self.bookmark(id)?;
+
+        let conn = &mut establish_connection(&self.path)?;
+
+        diesel::delete(btdsl::bookmarks_tags)
+            .filter(btdsl::bookmark_id.eq(id))
+            .execute(conn)
+            .map_err(|err| format!("{}", err))?;
+
+        self.insert_tags(id, &tags.to_vec())
+    }
+
+    /// Set url, description and tags of a bookmark to the state stored in a revision.
+    fn apply(&self, revision: &Revision) -> Result<(), String> {
+        self.set_url(revision.bookmark_id, &revision.url)?;
+        self.set_description(revision.bookmark_id, &revision.description)?;
+        self.set_tags(revision.bookmark_id, &revision.tags)
     }
 
     fn get_tags(&self, bookmark: &Bookmarks) -> Result<Vec<Tags>, String> {
@@ -117,9 +550,13 @@ impl BookmarkProxy for LocalProxy {
         let conn = &mut establish_connection(&self.path)?;
         let bookmark = bdsl::bookmarks
             .filter(bdsl::id.eq(id))
+            .filter(bdsl::deleted_at.is_null())
             .select(Bookmarks::as_select())
             .get_result(conn)
-            .map_err(|_| "Failed to load bookmark".to_string())?;
+            .map_err(|err| match err {
+                diesel::result::Error::NotFound => "Bookmark not found".to_string(),
+                err => format!("Failed to load bookmark: {:?}", err),
+            })?;
 
         Ok(Bookmark::new(&bookmark, &self.get_tags(&bookmark).unwrap()))
     }
@@ -129,6 +566,8 @@ impl BookmarkProxy for LocalProxy {
 
         let conn = &mut establish_connection(&self.path)?;
         let bs = bookmarks
+            .filter(deleted_at.is_null())
+            .select(Bookmarks::as_select())
             .load(conn)
             .map_err(|_| "Failed to load bookmarks".to_string())?;
 
@@ -138,85 +577,384 @@ impl BookmarkProxy for LocalProxy {
             .collect())
     }
 
-    fn add(&self, url: &str, description: &str, tags: Vec<String>) -> Result<(), String> {
+    fn add(&self, bookmark: &NewBookmark) -> Result<Bookmark, String> {
         use crate::schema::bookmarks::dsl as bdsl;
 
-        let conn = &mut establish_connection(&self.path)?;
+        self.check_duplicate(&bookmark.url, None)?;
 
-        let bs: Vec<Bookmarks> = bdsl::bookmarks
-            .filter(bdsl::url.eq(url))
-            .select(Bookmarks::as_select())
-            .get_results(conn)
-            .map_err(|err| format!("bs: {:?}", err))?;
-
-        if !bs.is_empty() {
-            return Err("Bookmark already exists".to_string());
-        }
+        let conn = &mut establish_connection(&self.path)?;
+        let key = self.url_key(&bookmark.url);
+        let metadata = bookmark
+            .metadata
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|err| format!("{}", err))?;
 
         insert_into(bdsl::bookmarks)
-            .values((bdsl::url.eq(url), bdsl::description.eq(description)))
+            .values((
+                bdsl::url.eq(&bookmark.url),
+                bdsl::description.eq(&bookmark.description),
+                bdsl::url_key.eq(&key),
+                bdsl::metadata.eq(metadata),
+            ))
             .execute(conn)
             .map_err(|_| "Failed to add bookmark".to_string())?;
 
         let bookmark_id: i32 = bdsl::bookmarks
-            .filter(bdsl::url.eq(url))
+            .filter(bdsl::url_key.eq(&key))
+            .filter(bdsl::deleted_at.is_null())
             .select(bdsl::id)
             .get_result(conn)
             .map_err(|err| format!("bookmark_id: {:?}", err))?;
 
-        self.insert_tags(bookmark_id, &tags).unwrap();
+        self.insert_tags(bookmark_id, &bookmark.tags)?;
+        self.record(bookmark_id, "add")?;
+
+        self.bookmark(bookmark_id)
+    }
 
+    fn delete(&self, id: i32) -> Result<(), String> {
+        self.set_deleted_at(id, Some(now()))?;
+        self.record(id, "delete")?;
+        self.purge_expired()?;
         Ok(())
     }
 
-    fn delete(&self, identifier: i32) -> Result<(), String> {
-        use crate::schema::bookmarks::dsl::*;
+    fn update_description(&self, id: i32, description: &str) -> Result<(), String> {
+        self.set_description(id, description)?;
+        self.record(id, "update")
+    }
 
-        let connection = &mut establish_connection(&self.path)?;
-        delete(bookmarks.filter(id.eq(identifier)))
-            .execute(connection)
-            .map_err(|_| "Failed to delete bookmark".to_string())?;
+    fn update_url(&self, id: i32, url: &str) -> Result<(), String> {
+        self.set_url(id, url)?;
+        self.record(id, "update")
+    }
 
-        Ok(())
+    fn update_tags(&self, id: i32, tags: &[String]) -> Result<(), String> {
+        self.set_tags(id, tags)?;
+        self.record(id, "update")
     }
 
-    fn update_description(&self, id: i32, description: &str) -> Result<(), String> {
+    fn trash(&self) -> Result<Vec<Bookmark>, String> {
         use crate::schema::bookmarks::dsl as bdsl;
 
+        self.purge_expired()?;
+
         let conn = &mut establish_connection(&self.path)?;
-        diesel::update(bdsl::bookmarks)
+        let bs = bdsl::bookmarks
+            .filter(bdsl::deleted_at.is_not_null())
+            .order(bdsl::deleted_at.desc())
+            .select(Bookmarks::as_select())
+            .load(conn)
+            .map_err(|_| "Failed to load trash".to_string())?;
+
+        bs.iter()
+            .map(|bookmark| Ok(Bookmark::new(bookmark, &self.get_tags(bookmark)?)))
+            .collect()
+    }
+
+    fn restore(&self, id: i32) -> Result<Bookmark, String> {
+        use crate::schema::bookmarks::dsl as bdsl;
+
+        let conn = &mut establish_connection(&self.path)?;
+        let url: String = bdsl::bookmarks
             .filter(bdsl::id.eq(id))
-            .set(bdsl::description.eq(description))
+            .filter(bdsl::deleted_at.is_not_null())
+            .select(bdsl::url)
+            .get_result(conn)
+            .map_err(|_| "Bookmark not found in trash".to_string())?;
+
+        self.check_duplicate(&url, Some(id))?;
+        self.set_deleted_at(id, None)?;
+        self.record(id, "restore")?;
+
+        self.bookmark(id)
+    }
+
+    fn empty_trash(&self) -> Result<usize, String> {
+        self.purge(None)
+    }
+
+    fn history(&self, id: i32) -> Result<Vec<Revision>, String> {
+        self.load(id)?;
+        self.revisions(id)?.into_iter().map(to_revision).collect()
+    }
+
+    fn revert(&self, id: i32, rev: i32) -> Result<Bookmark, String> {
+        let revision = self
+            .history(id)?
+            .into_iter()
+            .find(|revision| revision.rev == rev)
+            .ok_or("Revision not found".to_string())?;
+
+        self.apply(&revision)?;
+        self.record(id, "revert")?;
+
+        self.bookmark(id)
+    }
+
+    fn undo(&self) -> Result<Revision, String> {
+        use crate::schema::revisions::dsl as rdsl;
+
+        let conn = &mut establish_connection(&self.path)?;
+        let last = rdsl::revisions
+            .order(rdsl::id.desc())
+            .select(Revisions::as_select())
+            .first(conn)
+            .optional()
+            .map_err(|err| format!("{:?}", err))?
+            .ok_or("Nothing to undo".to_string())?;
+        let id = last.bookmark_id;
+        let last_id = last.id;
+        let last = to_revision(last)?;
+
+        match last.action.as_str() {
+            // Moved to the trash like a deletion, without recording one that the
+            // next undo would reverse
+            "add" => {
+                self.set_deleted_at(id, Some(now()))?;
+                self.purge_expired()?;
+            }
+            "delete" => {
+                self.check_duplicate(&last.url, Some(id))?;
+                self.set_deleted_at(id, None)?;
+            }
+            "restore" => self.set_deleted_at(id, Some(now()))?,
+            _ => {
+                let previous = self
+                    .history(id)?
+                    .into_iter()
+                    .rev()
+                    .find(|revision| revision.rev < last.rev)
+                    .ok_or("No previous revision".to_string())?;
+                self.apply(&previous)?;
+            }
+        }
+
+        delete(rdsl::revisions.filter(rdsl::id.eq(last_id)))
             .execute(conn)
-            .map_err(|_| "Failed to update description".to_string())?;
+            .map_err(|err| format!("{}", err))?;
+
+        Ok(last)
+    }
+
+    fn record_check(&self, check: &LinkCheck) -> Result<(), String> {
+        use crate::schema::link_checks::dsl as ldsl;
+
+        self.bookmark(check.bookmark_id)?;
+
+        let conn = &mut establish_connection(&self.path)?;
+        diesel::replace_into(ldsl::link_checks)
+            .values((
+                ldsl::bookmark_id.eq(check.bookmark_id),
+                ldsl::url.eq(&check.url),
+                ldsl::status.eq(check.status),
+                ldsl::redirect.eq(&check.redirect),
+                ldsl::error.eq(&check.error),
+                ldsl::checked_at.eq(check.checked_at),
+            ))
+            .execute(conn)
+            .map_err(|_| "Failed to record link check".to_string())?;
 
         Ok(())
     }
 
-    fn update_url(&self, id: i32, url: &str) -> Result<(), String> {
+    fn link_checks(&self) -> Result<Vec<LinkCheck>, String> {
         use crate::schema::bookmarks::dsl as bdsl;
+        use crate::schema::link_checks::dsl as ldsl;
 
         let conn = &mut establish_connection(&self.path)?;
-        diesel::update(bdsl::bookmarks)
-            .filter(bdsl::id.eq(id))
-            .set(bdsl::url.eq(url))
+        let checks = ldsl::link_checks
+            .inner_join(bdsl::bookmarks)
+            .filter(bdsl::deleted_at.is_null())
+            .order(ldsl::bookmark_id.asc())
+            .select(LinkChecks::as_select())
+            .load(conn)
+            .map_err(|_| "Failed to load link checks".to_string())?;
+
+        Ok(checks.into_iter().map(to_link_check).collect())
+    }
+
+    fn store_snapshot(&self, id: i32, content: &str) -> Result<Snapshot, String> {
+        use crate::schema::snapshots::dsl as sdsl;
+
+        let bookmark = self.bookmark(id)?;
+        let snapshot = Snapshot {
+            bookmark_id: id,
+            hash: self.archive().put(content)?,
+            url: bookmark.bookmark.url,
+            size: content.len() as i64,
+            created_at: now(),
+        };
+
+        let conn = &mut establish_connection(&self.path)?;
+        insert_into(sdsl::snapshots)
+            .values((
+                sdsl::bookmark_id.eq(snapshot.bookmark_id),
+                sdsl::hash.eq(&snapshot.hash),
+                sdsl::url.eq(&snapshot.url),
+                sdsl::size.eq(snapshot.size),
+                sdsl::created_at.eq(snapshot.created_at),
+            ))
             .execute(conn)
-            .map_err(|_| "Failed to update description".to_string())?;
+            .map_err(|_| "Failed to record snapshot".to_string())?;
+        self.set_page_text(id, &page_text(content))?;
 
-        Ok(())
+        Ok(snapshot)
     }
 
-    fn update_tags(&self, id: i32, tags: &[String]) -> Result<(), String> {
-        use crate::schema::bookmarks_tags::dsl as btdsl;
+    fn snapshots(&self, id: i32) -> Result<Vec<Snapshot>, String> {
+        use crate::schema::snapshots::dsl as sdsl;
+
+        self.bookmark(id)?;
 
         let conn = &mut establish_connection(&self.path)?;
+        let snapshots = sdsl::snapshots
+            .filter(sdsl::bookmark_id.eq(id))
+            .order((sdsl::created_at.desc(), sdsl::id.desc()))
+            .select(Snapshots::as_select())
+            .load(conn)
+            .map_err(|_| "Failed to load snapshots".to_string())?;
 
-        diesel::delete(btdsl::bookmarks_tags)
-            .filter(btdsl::bookmark_id.eq(id))
+        Ok(snapshots.into_iter().map(to_snapshot).collect())
+    }
+
+    fn snapshot(&self, hash: &str) -> Result<String, String> {
+        use crate::schema::snapshots::dsl as sdsl;
+
+        let conn = &mut establish_connection(&self.path)?;
+        let references: i64 = sdsl::snapshots
+            .filter(sdsl::hash.eq(hash))
+            .count()
+            .get_result(conn)
+            .map_err(|err| format!("{:?}", err))?;
+        if references == 0 {
+            return Err("Snapshot not found".to_string());
+        }
+
+        self.archive().get(hash)
+    }
+
+    fn set_page_text(&self, id: i32, text: &str) -> Result<(), String> {
+        self.bookmark(id)?;
+
+        let conn = &mut establish_connection(&self.path)?;
+        sql_query("delete from page_text where rowid = ?")
+            .bind::<Integer, _>(id)
             .execute(conn)
             .map_err(|err| format!("{}", err))?;
+        sql_query("insert into page_text (rowid, text) values (?, ?)")
+            .bind::<Integer, _>(id)
+            .bind::<Text, _>(text)
+            .execute(conn)
+            .map_err(|_| "Failed to index page text".to_string())?;
 
-        self.insert_tags(id, &tags.to_vec())?;
         Ok(())
     }
+
+    fn search(&self, query: &str) -> Result<Vec<SearchResult>, String> {
+        let fts_query = fts_query(query).ok_or("Empty search query".to_string())?;
+
+        let conn = &mut establish_connection(&self.path)?;
+        let mut pages: Vec<PageMatches> = sql_query(
+            "select bookmarks.id as id, \
+             snippet(page_text, 0, '<mark>', '</mark>', '…', 16) as snippet \
+             from page_text join bookmarks on bookmarks.id = page_text.rowid \
+             where page_text match ? and bookmarks.deleted_at is null \
+             order by rank",
+        )
+        .bind::<Text, _>(fts_query)
+        .load(conn)
+        .map_err(|err| format!("Failed to search pages: {}", err))?;
+
+        let mut results = vec![];
+        for bookmark in self.bookmarks()? {
+            if bookmark.matches(query) {
+                let page = pages
+                    .iter()
+                    .position(|page| page.id == bookmark.bookmark.id);
+                results.push(SearchResult {
+                    bookmark,
+                    snippet: page.map(|page| pages.remove(page).snippet),
+                });
+            }
+        }
+        results.sort_by_cached_key(|result| {
+            Reverse(fuzzy::bookmark_score(query, &result.bookmark).unwrap_or(0))
+        });
+        for page in pages {
+            results.push(SearchResult {
+                bookmark: self.bookmark(page.id)?,
+                snippet: Some(page.snippet),
+            });
+        }
+
+        Ok(results)
+    }
+
+    fn visit(&self, id: i32) -> Result<(), String> {
+        use crate::schema::visits::dsl as vdsl;
+
+        self.bookmark(id)?;
+
+        let conn = &mut establish_connection(&self.path)?;
+        insert_into(vdsl::visits)
+            .values((vdsl::bookmark_id.eq(id), vdsl::visited_at.eq(now())))
+            .execute(conn)
+            .map_err(|_| "Failed to record visit".to_string())?;
+
+        Ok(())
+    }
+
+    fn visits(&self) -> Result<Vec<Visits>, String> {
+        use crate::schema::bookmarks::dsl as bdsl;
+        use crate::schema::visits::dsl as vdsl;
+
+        let conn = &mut establish_connection(&self.path)?;
+        let rows: Vec<(i32, i64)> = vdsl::visits
+            .inner_join(bdsl::bookmarks)
+            .filter(bdsl::deleted_at.is_null())
+            .order((vdsl::bookmark_id.asc(), vdsl::visited_at.desc()))
+            .select((vdsl::bookmark_id, vdsl::visited_at))
+            .load(conn)
+            .map_err(|_| "Failed to load visits".to_string())?;
+
+        let now = now();
+        Ok(rows
+            .chunk_by(|a, b| a.0 == b.0)
+            .map(|visits| {
+                let bookmark_id = visits[0].0;
+                let visits: Vec<i64> = visits.iter().map(|(_, visited_at)| *visited_at).collect();
+                Visits {
+                    bookmark_id,
+                    count: visits.len() as i64,
+                    last_visit: visits[0],
+                    frecency: frecency(&visits, visits.len() as i64, now),
+                }
+            })
+            .collect())
+    }
+
+    fn tags(&self) -> Result<Vec<TagCount>, String> {
+        use crate::schema::bookmarks::dsl as bdsl;
+        use crate::schema::bookmarks_tags::dsl as btdsl;
+        use crate::schema::tags::dsl as tdsl;
+
+        let conn = &mut establish_connection(&self.path)?;
+        let counts: Vec<(String, i64)> = btdsl::bookmarks_tags
+            .inner_join(tdsl::tags)
+            .inner_join(bdsl::bookmarks)
+            .filter(bdsl::deleted_at.is_null())
+            .group_by(tdsl::tag)
+            .select((tdsl::tag, diesel::dsl::count_star()))
+            .order(tdsl::tag.asc())
+            .load(conn)
+            .map_err(|_| "Failed to load tags".to_string())?;
+
+        Ok(counts
+            .into_iter()
+            .map(|(tag, count)| TagCount { tag, count })
+            .collect())
+    }
 }
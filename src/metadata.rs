@@ -0,0 +1,232 @@
+// Copyright 2024 João Freitas
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of
+// the GNU General Public License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS FOR
+// A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Information about bookmarked pages fetched from the web.
+
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use std::io::Read;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// Bytes of a page read at most, metadata is found at the start of the html
+const MAX_PAGE_SIZE: u64 = 1024 * 1024;
+
+/// Metadata extracted from the html of a page
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PageMetadata {
+    /// Contents of `<title>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Open Graph title
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub og_title: Option<String>,
+    /// Page summary from `<meta name="description">` or `og:description`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canonical_url: Option<String>,
+    /// Absolute url of the page icon
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub favicon: Option<String>,
+}
+
+impl PageMetadata {
+    /// Title to use as the description of a bookmark
+    pub fn best_title(&self) -> Option<String> {
+        self.og_title.clone().or_else(|| self.title.clone())
+    }
+}
+
+fn selector(selector: &str) -> Selector {
+    Selector::parse(selector).expect("Invariant: selectors are valid css")
+}
+
+fn clean(text: &str) -> Option<String> {
+    let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+    if text.is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+fn attr(document: &Html, css: &str, name: &str) -> Option<String> {
+    document
+        .select(&selector(css))
+        .find_map(|element| element.value().attr(name).and_then(clean))
+}
+
+fn has_rel(element: &scraper::ElementRef, rel: &str) -> bool {
+    element.value().attr("rel").is_some_and(|value| {
+        value
+            .split_whitespace()
+            .any(|token| token.eq_ignore_ascii_case(rel))
+    })
+}
+
+/// Extract metadata from the html of the page at `base`, relative urls are
+/// resolved against it.
+pub fn extract(html: &str, base: &Url) -> PageMetadata {
+    let document = Html::parse_document(html);
+    let resolve = |href: String| base.join(&href).ok().map(|url| url.to_string());
+    let links: Vec<scraper::ElementRef> = document.select(&selector("link[href]")).collect();
+
+    let favicon = links
+        .iter()
+        .find(|link| has_rel(link, "icon"))
+        .or_else(|| links.iter().find(|link| has_rel(link, "apple-touch-icon")))
+        .and_then(|link| link.value().attr("href"))
+        .map(|href| href.to_string())
+        .or_else(|| match base.scheme() {
+            "http" | "https" => Some("/favicon.ico".to_string()),
+            _ => None,
+        })
+        .and_then(resolve);
+
+    PageMetadata {
+        title: document
+            .select(&selector("title"))
+            .find_map(|title| clean(&title.text().collect::<String>())),
+        og_title: attr(&document, r#"meta[property="og:title"]"#, "content"),
+        description: attr(&document, r#"meta[name="description"]"#, "content")
+            .or_else(|| attr(&document, r#"meta[property="og:description"]"#, "content")),
+        canonical_url: links
+            .iter()
+            .find(|link| has_rel(link, "canonical"))
+            .and_then(|link| link.value().attr("href"))
+            .and_then(|href| resolve(href.to_string())),
+        favicon,
+    }
+}
+
+/// Download `url` and extract its metadata.
+pub fn fetch(url: &str) -> Result<PageMetadata, String> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(TIMEOUT)
+        .build()
+        .map_err(|err| format!("{}", err))?;
+    let response = client
+        .get(url)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| format!("Failed to fetch {}: {}", url, err))?;
+
+    let base = response.url().clone();
+    let mut html = vec![];
+    response
+        .take(MAX_PAGE_SIZE)
+        .read_to_end(&mut html)
+        .map_err(|err| format!("Failed to read {}: {}", url, err))?;
+
+    Ok(extract(&String::from_utf8_lossy(&html), &base))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    const PAGE: &str = r#"<!doctype html>
+<html>
+  <head>
+    <title>
+      Rust &amp; Cargo
+    </title>
+    <meta name="description" content="A language empowering everyone">
+    <meta property="og:title" content="Rust Programming Language">
+    <link rel="canonical" href="/en-US/">
+    <link rel="stylesheet" href="/style.css">
+    <link rel="shortcut icon" href="static/favicon.png">
+  </head>
+  <body><title>Not the title</title></body>
+</html>"#;
+
+    /// Serve `body` for a single request and return the url to request.
+    fn serve(body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request).unwrap();
+            // The client may hang up before reading large bodies
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+        });
+
+        format!("http://{}/docs/index.html", addr)
+    }
+
+    #[test]
+    fn metadata_is_extracted() {
+        let base = Url::parse("https://www.rust-lang.org/learn/").unwrap();
+        assert_eq!(
+            extract(PAGE, &base),
+            PageMetadata {
+                title: Some("Rust & Cargo".to_string()),
+                og_title: Some("Rust Programming Language".to_string()),
+                description: Some("A language empowering everyone".to_string()),
+                canonical_url: Some("https://www.rust-lang.org/en-US/".to_string()),
+                favicon: Some("https://www.rust-lang.org/learn/static/favicon.png".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn missing_metadata_falls_back() {
+        let base = Url::parse("https://example.com/a/b").unwrap();
+        let metadata = extract("<p>Nothing here</p>", &base);
+
+        assert_eq!(metadata.best_title(), None);
+        assert_eq!(metadata.canonical_url, None);
+        assert_eq!(
+            metadata.favicon,
+            Some("https://example.com/favicon.ico".to_string())
+        );
+    }
+
+    #[test]
+    fn metadata_is_fetched() {
+        let url = serve(PAGE);
+        let metadata = fetch(&url).unwrap();
+
+        assert_eq!(
+            metadata.best_title(),
+            Some("Rust Programming Language".to_string())
+        );
+        assert_eq!(
+            metadata.favicon,
+            Some(url.replace("index.html", "static/favicon.png"))
+        );
+        assert!(fetch("http://127.0.0.1:1/").is_err());
+    }
+
+    #[test]
+    fn only_the_start_of_pages_is_read() {
+        let body = PAGE.replace("<body>", &format!("<body>{}", "x".repeat(4 << 20)));
+        let metadata = fetch(&serve(body.leak())).unwrap();
+
+        assert_eq!(metadata.title, Some("Rust & Cargo".to_string()));
+    }
+}
@@ -22,6 +22,12 @@ pub struct Bookmarks {
     pub id: i32,
     pub url: String,
     pub description: String,
+    /// Unix timestamp of when the bookmark was moved to the trash
+    #[serde(default)]
+    pub deleted_at: Option<i64>,
+    /// Page metadata fetched when the bookmark was added, as json
+    #[serde(default)]
+    pub metadata: Option<String>,
 }
 
 #[derive(Queryable, Selectable, Serialize, Deserialize, Debug, Clone)]
@@ -40,3 +46,50 @@ pub struct BookmarkTags {
     pub bookmark_id: i32,
     pub tag_id: i32,
 }
+
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::revisions)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Revisions {
+    pub id: i32,
+    pub bookmark_id: i32,
+    pub rev: i32,
+    pub action: String,
+    pub url: String,
+    pub description: String,
+    pub tags: String,
+    pub created_at: i64,
+}
+
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::link_checks)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct LinkChecks {
+    pub bookmark_id: i32,
+    pub url: String,
+    pub status: Option<i32>,
+    pub redirect: Option<String>,
+    pub error: Option<String>,
+    pub checked_at: i64,
+}
+
+#[derive(Queryable, Selectable, Serialize, Deserialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::snapshots)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Snapshots {
+    pub id: i32,
+    pub bookmark_id: i32,
+    pub hash: String,
+    pub url: String,
+    pub size: i64,
+    pub created_at: i64,
+}
+
+/// Bookmark matched by a full text search of page text
+#[derive(QueryableByName, Debug)]
+pub struct PageMatches {
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    pub id: i32,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub snippet: String,
+}
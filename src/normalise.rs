@@ -0,0 +1,195 @@
+// Copyright 2024 João Freitas
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of
+// the GNU General Public License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS FOR
+// A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Url normalisation used to detect bookmarks that point to the same page.
+
+use itertools::Itertools;
+use url::Url;
+
+use crate::bookmark::Bookmark;
+use crate::bookmark_proxy::BookmarkProxy;
+
+/// Query parameters stripped from urls unless configured otherwise. A trailing
+/// `*` matches any parameter starting with the given prefix.
+pub const DEFAULT_TRACKING_PARAMS: &[&str] = &[
+    "utm_*", "fbclid", "gclid", "dclid", "msclkid", "mc_cid", "mc_eid", "ref_src", "igshid",
+];
+
+pub fn default_tracking_params() -> Vec<String> {
+    DEFAULT_TRACKING_PARAMS
+        .iter()
+        .map(|param| param.to_string())
+        .collect()
+}
+
+fn is_tracking_param(name: &str, tracking_params: &[String]) -> bool {
+    tracking_params
+        .iter()
+        .any(|param| match param.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => name == param,
+        })
+}
+
+/// Compute the key under which a url is considered unique.
+///
+/// Scheme and host case, default ports, `http` versus `https`, trailing
+/// slashes, the order of query parameters and tracking parameters are all
+/// ignored. Urls that fail to parse are only trimmed.
+pub fn url_key(url: &str, tracking_params: &[String]) -> String {
+    let url = url.trim();
+    let parsed = match Url::parse(url) {
+        Ok(parsed) => parsed,
+        Err(_) => return url.to_string(),
+    };
+
+    let scheme = match parsed.scheme() {
+        "http" | "https" => "",
+        scheme => scheme,
+    };
+
+    let mut key = format!("{}:", scheme);
+    if let Some(host) = parsed.host_str() {
+        key += "//";
+        key += host;
+        if let Some(port) = parsed.port() {
+            key += &format!(":{}", port);
+        }
+    }
+    key += parsed.path().trim_end_matches('/');
+
+    let query = parsed
+        .query_pairs()
+        .filter(|(name, _)| !is_tracking_param(name, tracking_params))
+        .map(|(name, value)| {
+            url::form_urlencoded::Serializer::new(String::new())
+                .append_pair(&name, &value)
+                .finish()
+        })
+        .sorted()
+        .join("&");
+    if !query.is_empty() {
+        key += "?";
+        key += &query;
+    }
+
+    if let Some(fragment) = parsed.fragment().filter(|fragment| !fragment.is_empty()) {
+        key += "#";
+        key += fragment;
+    }
+
+    key
+}
+
+/// Group bookmarks whose urls share the same key, keeping only groups with
+/// more than one bookmark. Bookmarks inside a group are ordered by id.
+pub fn duplicates(bookmarks: &[Bookmark], tracking_params: &[String]) -> Vec<Vec<Bookmark>> {
+    bookmarks
+        .iter()
+        .sorted_by_key(|bookmark| bookmark.bookmark.id)
+        .into_group_map_by(|bookmark| url_key(&bookmark.bookmark.url, tracking_params))
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .map(|group| group.into_iter().cloned().collect::<Vec<Bookmark>>())
+        .sorted_by_key(|group| group[0].bookmark.id)
+        .collect()
+}
+
+/// Merge each group of near-duplicate bookmarks into its oldest bookmark,
+/// which receives the union of the group's tags. Returns the merged groups.
+pub fn dedupe(
+    proxy: &dyn BookmarkProxy,
+    tracking_params: &[String],
+) -> Result<Vec<Vec<Bookmark>>, String> {
+    let groups = duplicates(&proxy.bookmarks()?, tracking_params);
+
+    for group in &groups {
+        let keep = &group[0];
+        let tags: Vec<String> = group
+            .iter()
+            .flat_map(|bookmark| bookmark.tags.iter().map(|tag| tag.tag.clone()))
+            .unique()
+            .collect();
+
+        if keep.bookmark.description.is_empty() {
+            if let Some(other) = group
+                .iter()
+                .find(|bookmark| !bookmark.bookmark.description.is_empty())
+            {
+                proxy.update_description(keep.bookmark.id, &other.bookmark.description)?;
+            }
+        }
+        proxy.update_tags(keep.bookmark.id, &tags)?;
+
+        for duplicate in &group[1..] {
+            proxy.delete(duplicate.bookmark.id)?;
+        }
+    }
+
+    Ok(groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(url: &str) -> String {
+        url_key(url, &default_tracking_params())
+    }
+
+    #[test]
+    fn equivalent_urls_share_a_key() {
+        let expected = key("https://x.com/a");
+        for url in [
+            "http://x.com/a",
+            "https://x.com/a/",
+            "HTTPS://X.COM/a",
+            "https://x.com:443/a",
+            "http://x.com:80/a",
+            "https://x.com/a?utm_source=foo&utm_medium=bar",
+            "https://x.com/a?fbclid=123#",
+        ] {
+            assert_eq!(key(url), expected, "{}", url);
+        }
+    }
+
+    #[test]
+    fn meaningful_differences_are_kept() {
+        assert_ne!(key("https://x.com/a"), key("https://x.com/A"));
+        assert_ne!(key("https://x.com/a"), key("https://x.com/a?page=2"));
+        assert_ne!(key("https://x.com/a"), key("https://x.com:8080/a"));
+        assert_ne!(key("https://x.com/a"), key("https://x.com/a#section"));
+        assert_ne!(key("https://x.com/a"), key("ftp://x.com/a"));
+    }
+
+    #[test]
+    fn query_parameters_are_sorted() {
+        assert_eq!(
+            key("https://x.com/?b=2&a=1&utm_campaign=z"),
+            key("https://x.com?a=1&b=2")
+        );
+    }
+
+    #[test]
+    fn tracking_params_are_configurable() {
+        let params = vec!["ref".to_string()];
+        assert_eq!(
+            url_key("https://x.com/?ref=hn", &params),
+            url_key("https://x.com", &params)
+        );
+        assert_ne!(
+            url_key("https://x.com/?utm_source=a", &params),
+            url_key("https://x.com", &params)
+        );
+    }
+}
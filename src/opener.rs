@@ -0,0 +1,301 @@
+// Copyright 2024 João Freitas
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of
+// the GNU General Public License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS FOR
+// A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Opening bookmarks with the command chosen by the `[open]` configuration.
+//!
+//! Command templates are split into arguments like a shell would, without
+//! running one. `{url}` and `{host}` are replaced by the url of the bookmark and
+//! its host, the url is appended when the template doesn't mention it.
+
+use directories::BaseDirs;
+use url::Url;
+
+use std::fs::{self, DirBuilder, Permissions};
+use std::io::Write;
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::bookmark::Bookmark;
+use crate::bookmark_proxy::BookmarkProxy;
+use crate::config::{OpenConfig, OpenRule};
+
+impl OpenRule {
+    fn matches(&self, url: Option<&Url>, tags: &[String]) -> bool {
+        let tag = self
+            .tag
+            .as_ref()
+            .is_none_or(|rule| tags.iter().any(|tag| tag == rule));
+        let domain = self.domain.as_ref().is_none_or(|rule| {
+            url.and_then(Url::host_str).is_some_and(|host| {
+                let host = host.to_lowercase();
+                let rule = rule.to_lowercase();
+                host == rule || host.ends_with(&format!(".{}", rule))
+            })
+        });
+        let scheme = self
+            .scheme
+            .as_ref()
+            .is_none_or(|rule| url.is_some_and(|url| url.scheme().eq_ignore_ascii_case(rule)));
+
+        tag && domain && scheme
+    }
+}
+
+impl OpenConfig {
+    /// Template of the command that opens `url`, none to use the system handler.
+    pub fn command_for(&self, url: &str, tags: &[String]) -> Option<&str> {
+        let parsed = Url::parse(url).ok();
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(parsed.as_ref(), tags))
+            .map(|rule| rule.command.as_str())
+            .or(self.command.as_deref())
+    }
+}
+
+/// Split `template` into arguments, honouring quotes and backslash escapes.
+fn split(template: &str) -> Result<Vec<String>, String> {
+    let mut args = vec![];
+    let mut arg: Option<String> = None;
+    let mut quote: Option<char> = None;
+    let mut chars = template.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                arg.get_or_insert_with(String::new);
+            }
+            (Some('\''), c) => arg.get_or_insert_with(String::new).push(c),
+            (_, '\\') => {
+                let escaped = chars.next().ok_or("Trailing backslash in open command")?;
+                arg.get_or_insert_with(String::new).push(escaped);
+            }
+            (None, c) if c.is_whitespace() => args.extend(arg.take()),
+            (_, c) => arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    if quote.is_some() {
+        return Err(format!("Unterminated quote in open command: {}", template));
+    }
+    args.extend(arg);
+    Ok(args)
+}
+
+/// Arguments of the command that opens `url` with `template`.
+pub fn command_line(template: &str, url: &str) -> Result<Vec<String>, String> {
+    let host = Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| host.to_string()))
+        .unwrap_or_default();
+
+    let mut args: Vec<String> = split(template)?
+        .into_iter()
+        .map(|arg| arg.replace("{url}", url).replace("{host}", &host))
+        .collect();
+    if args.is_empty() {
+        return Err("Empty open command".to_string());
+    }
+    if !template.contains("{url}") {
+        args.push(url.to_string());
+    }
+
+    Ok(args)
+}
+
+/// Open `url` with `template`, or with the system handler if none.
+pub fn open_url(template: Option<&str>, url: &str) -> Result<(), String> {
+    match template {
+        Some(template) => {
+            let args = command_line(template, url)?;
+            Command::new(&args[0])
+                .args(&args[1..])
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .spawn()
+                .map_err(|err| format!("Failed to run {}: {}", args[0], err))?;
+            Ok(())
+        }
+        None => open::that(url).map_err(|_| "Failed to open url".to_string()),
+    }
+}
+
+/// Open `bookmark` with the first matching rule of `config`.
+pub fn open_bookmark(config: &OpenConfig, bookmark: &Bookmark) -> Result<(), String> {
+    let url = &bookmark.bookmark.url;
+    open_url(config.command_for(url, &bookmark.tag_names()), url)
+}
+
+/// Directory of the snapshots opened locally, only readable by the user.
+fn snapshot_dir() -> Result<PathBuf, String> {
+    let dir = BaseDirs::new()
+        .ok_or("Could not find the cache directory")?
+        .cache_dir()
+        .join("marcador")
+        .join("snapshots");
+    DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(&dir)
+        .and_then(|_| fs::set_permissions(&dir, Permissions::from_mode(0o700)))
+        .map_err(|err| format!("Could not create {}: {}", dir.display(), err))?;
+    Ok(dir)
+}
+
+/// File in `dir` holding snapshot `hash`, written from `content` unless an
+/// earlier open left it there.
+fn snapshot_file(
+    dir: &Path,
+    hash: &str,
+    content: impl FnOnce() -> Result<String, String>,
+) -> Result<PathBuf, String> {
+    if hash.is_empty() || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid snapshot hash {}", hash));
+    }
+    let path = dir.join(format!("{}.html", hash));
+    if path.exists() {
+        return Ok(path);
+    }
+
+    let mut file = tempfile::NamedTempFile::new_in(dir)
+        .map_err(|err| format!("Could not create temporary file: {}", err))?;
+    file.write_all(content()?.as_bytes())
+        .map_err(|err| format!("Could not write temporary file: {}", err))?;
+    file.persist(&path)
+        .map_err(|err| format!("Could not save {}: {}", path.display(), err))?;
+    Ok(path)
+}
+
+/// Open the latest snapshot of bookmark `id` from a copy in the cache
+/// directory, which the browser may read after this returns.
+pub fn open_archived(
+    config: &OpenConfig,
+    proxy: &dyn BookmarkProxy,
+    id: i32,
+) -> Result<(), String> {
+    let snapshot = proxy
+        .snapshots(id)?
+        .into_iter()
+        .next()
+        .ok_or(format!("Bookmark {} has no offline copy", id))?;
+
+    let path = snapshot_file(&snapshot_dir()?, &snapshot.hash, || {
+        proxy.snapshot(&snapshot.hash)
+    })?;
+    open_url(
+        config.command.as_deref(),
+        &format!("file://{}", path.display()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(
+        tag: Option<&str>,
+        domain: Option<&str>,
+        scheme: Option<&str>,
+        command: &str,
+    ) -> OpenRule {
+        OpenRule {
+            tag: tag.map(str::to_string),
+            domain: domain.map(str::to_string),
+            scheme: scheme.map(str::to_string),
+            command: command.to_string(),
+        }
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let config = OpenConfig {
+            command: Some("firefox".to_string()),
+            rules: vec![
+                rule(Some("work"), None, None, "firefox -P work"),
+                rule(None, Some("docs.rs"), None, "firefox -P docs"),
+                rule(None, None, Some("ssh"), "alacritty -e ssh {host}"),
+                rule(Some("docs"), Some("rust-lang.org"), None, "firefox -P docs"),
+            ],
+        };
+        let work = vec!["work".to_string()];
+        let docs = vec!["docs".to_string()];
+
+        assert_eq!(
+            config.command_for("https://docs.rs/url", &work),
+            Some("firefox -P work")
+        );
+        assert_eq!(
+            config.command_for("https://serde.docs.rs", &[]),
+            Some("firefox -P docs")
+        );
+        assert_eq!(
+            config.command_for("https://notdocs.rs", &[]),
+            Some("firefox")
+        );
+        assert_eq!(
+            config.command_for("SSH://example.com", &[]),
+            Some("alacritty -e ssh {host}")
+        );
+        assert_eq!(
+            config.command_for("https://doc.rust-lang.org", &docs),
+            Some("firefox -P docs")
+        );
+        assert_eq!(
+            config.command_for("https://rust-lang.org", &[]),
+            Some("firefox")
+        );
+        assert_eq!(
+            OpenConfig::default().command_for("https://a.com", &[]),
+            None
+        );
+    }
+
+    #[test]
+    fn templates_are_expanded() {
+        assert_eq!(
+            command_line("firefox -P work", "https://a.com").unwrap(),
+            vec!["firefox", "-P", "work", "https://a.com"]
+        );
+        assert_eq!(
+            command_line(r#"sh -c 'echo "$0"' {url}"#, "https://a.com/?q=1 2").unwrap(),
+            vec!["sh", "-c", r#"echo "$0""#, "https://a.com/?q=1 2"]
+        );
+        assert_eq!(
+            command_line(r#"term --title "ssh {host}" -e ssh {url}"#, "ssh://box").unwrap(),
+            vec!["term", "--title", "ssh box", "-e", "ssh", "ssh://box"]
+        );
+        assert_eq!(
+            command_line(r#"a\ b ''"#, "x").unwrap(),
+            vec!["a b", "", "x"]
+        );
+        assert!(command_line("firefox 'oops", "x").is_err());
+        assert!(command_line(" ", "x").is_err());
+    }
+
+    #[test]
+    fn snapshot_files_are_reused() {
+        let dir = tempfile::tempdir().unwrap();
+        let hash = "726098488acb8eeeb38f80de478fa602d9feebc3";
+
+        let path = snapshot_file(dir.path(), hash, || Ok("<p>page</p>".to_string())).unwrap();
+        assert_eq!(path, dir.path().join(format!("{}.html", hash)));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "<p>page</p>");
+
+        let again = snapshot_file(dir.path(), hash, || Err("fetched again".to_string()));
+        assert_eq!(again, Ok(path));
+        assert!(snapshot_file(dir.path(), "../../etc/passwd", || Ok(String::new())).is_err());
+    }
+}
@@ -1,4 +1,4 @@
-// Copyright 2024 João FreitasA
+// Copyright 2024 João Freitas
 //
 // This program is free software: you can redistribute it and/or modify it under the terms of
 // the GNU General Public License as published by the Free Software Foundation, either
@@ -11,109 +11,177 @@
 // You should have received a copy of the GNU General Public License along with this
 // program. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::bookmark::Bookmark;
+use reqwest::blocking::{Client, RequestBuilder};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::bookmark::{
+    Bookmark, LinkCheck, NewBookmark, Revision, SearchResult, Snapshot, TagCount, Visits,
+};
 use crate::bookmark_proxy::BookmarkProxy;
 
 use crate::server::{AddParams, DeleteParams};
 
 pub struct RemoteProxy {
-    bookmark_endpoint: String,
-    list_endpoint: String,
-    add_endpoint: String,
-    delete_endpoint: String,
-    update_description_endpoint: String,
-    update_url_endpoint: String,
-    update_tags_endpoint: String,
+    url: String,
+    client: Client,
 }
 
 impl RemoteProxy {
     pub fn new(url: &str) -> Self {
         Self {
-            bookmark_endpoint: url.to_string() + "/bookmark",
-            list_endpoint: url.to_string() + "/list",
-            add_endpoint: url.to_string() + "/add",
-            delete_endpoint: url.to_string() + "/delete",
-            update_description_endpoint: url.to_string() + "/update_description",
-            update_url_endpoint: url.to_string() + "/update_url",
-            update_tags_endpoint: url.to_string() + "/update_tags",
+            url: url.trim_end_matches('/').to_string(),
+            client: Client::new(),
         }
     }
-}
 
-impl BookmarkProxy for RemoteProxy {
-    fn bookmark(&self, id: i32) -> Result<Bookmark, String> {
-        let client = reqwest::blocking::Client::new();
-        let response = client
-            .get(&self.bookmark_endpoint)
-            .json(&id)
+    fn endpoint(&self, name: &str) -> String {
+        format!("{}/{}", self.url, name)
+    }
+
+    /// Send a request and decode its json response, turning error statuses into
+    /// the error message returned by the server.
+    fn send<R: DeserializeOwned>(request: RequestBuilder) -> Result<R, String> {
+        let response = request
             .send()
-            .map_err(|_| "Failed to send get request")?;
+            .map_err(|err| format!("Failed to send request: {}", err))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            return Err(response
+                .text()
+                .ok()
+                .filter(|text| !text.is_empty())
+                .unwrap_or_else(|| format!("Server responded with {}", status)));
+        }
 
         response
-            .json::<Bookmark>()
-            .map_err(|err| format!("{}", err))
+            .json::<R>()
+            .map_err(|err| format!("Failed to parse json: {}", err))
+    }
+
+    fn get<P: Serialize + ?Sized, R: DeserializeOwned>(
+        &self,
+        name: &str,
+        params: &P,
+    ) -> Result<R, String> {
+        Self::send(self.client.get(self.endpoint(name)).json(params))
+    }
+
+    fn post<P: Serialize + ?Sized, R: DeserializeOwned>(
+        &self,
+        name: &str,
+        params: &P,
+    ) -> Result<R, String> {
+        Self::send(self.client.post(self.endpoint(name)).json(params))
+    }
+}
+
+impl BookmarkProxy for RemoteProxy {
+    fn bookmark(&self, id: i32) -> Result<Bookmark, String> {
+        self.get("bookmark", &id)
     }
 
     fn bookmarks(&self) -> Result<Vec<Bookmark>, String> {
-        Ok(reqwest::blocking::get(&self.list_endpoint)
-            .map_err(|_| "Failed to get web resource")?
-            .json::<Vec<Bookmark>>()
-            .map_err(|_| "Failed to parse json")?)
-    }
-
-    fn add(&self, link: &str, desc: &str, _tags: Vec<String>) -> Result<(), String> {
-        let client = reqwest::blocking::Client::new();
-        client
-            .post(&self.add_endpoint)
-            .json(&AddParams {
-                url: link.to_string(),
-                description: desc.to_string(),
-            })
-            .send()
-            .map_err(|_| "Failed to send post request")?;
+        Self::send(self.client.get(self.endpoint("list")))
+    }
 
-        Ok(())
+    fn add(&self, bookmark: &NewBookmark) -> Result<Bookmark, String> {
+        self.post(
+            "add",
+            &AddParams {
+                url: bookmark.url.clone(),
+                description: bookmark.description.clone(),
+                tags: bookmark.tags.clone(),
+                metadata: bookmark.metadata.clone(),
+            },
+        )
     }
-    fn delete(&self, identifier: i32) -> Result<(), String> {
-        let client = reqwest::blocking::Client::new();
-        client
-            .post(&self.delete_endpoint)
-            .json(&DeleteParams { id: identifier })
-            .send()
-            .map_err(|_| "Failed to send post request")?;
 
+    fn delete(&self, identifier: i32) -> Result<(), String> {
+        self.post::<_, i32>("delete", &DeleteParams { id: identifier })?;
         Ok(())
     }
 
     fn update_description(&self, id: i32, description: &str) -> Result<(), String> {
-        let client = reqwest::blocking::Client::new();
-        client
-            .post(&self.update_description_endpoint)
-            .json(&(id, description))
-            .send()
-            .map_err(|_| "Failed to send post request")?;
-
+        self.post::<_, i32>("update_description", &(id, description))?;
         Ok(())
     }
 
     fn update_url(&self, id: i32, url: &str) -> Result<(), String> {
-        let client = reqwest::blocking::Client::new();
-        client
-            .post(&self.update_url_endpoint)
-            .json(&(id, url))
-            .send()
-            .map_err(|_| "Failed to send post request")?;
-
+        self.post::<_, i32>("update_url", &(id, url))?;
         Ok(())
     }
+
     fn update_tags(&self, id: i32, tags: &[String]) -> Result<(), String> {
-        let client = reqwest::blocking::Client::new();
-        client
-            .post(&self.update_tags_endpoint)
-            .json(&(id, tags))
-            .send()
-            .map_err(|_| "Failed to send post request")?;
+        self.post::<_, i32>("update_tags", &(id, tags))?;
+        Ok(())
+    }
 
+    fn trash(&self) -> Result<Vec<Bookmark>, String> {
+        Self::send(self.client.get(self.endpoint("trash")))
+    }
+
+    fn restore(&self, id: i32) -> Result<Bookmark, String> {
+        self.post("restore", &id)
+    }
+
+    fn empty_trash(&self) -> Result<usize, String> {
+        self.post("empty_trash", &())
+    }
+
+    fn history(&self, id: i32) -> Result<Vec<Revision>, String> {
+        self.get("history", &id)
+    }
+
+    fn revert(&self, id: i32, rev: i32) -> Result<Bookmark, String> {
+        self.post("revert", &(id, rev))
+    }
+
+    fn undo(&self) -> Result<Revision, String> {
+        self.post("undo", &())
+    }
+
+    fn record_check(&self, check: &LinkCheck) -> Result<(), String> {
+        self.post::<_, i32>("record_check", check)?;
         Ok(())
     }
+
+    fn link_checks(&self) -> Result<Vec<LinkCheck>, String> {
+        Self::send(self.client.get(self.endpoint("link_checks")))
+    }
+
+    fn store_snapshot(&self, id: i32, content: &str) -> Result<Snapshot, String> {
+        self.post("store_snapshot", &(id, content))
+    }
+
+    fn snapshots(&self, id: i32) -> Result<Vec<Snapshot>, String> {
+        self.get("snapshots", &id)
+    }
+
+    fn snapshot(&self, hash: &str) -> Result<String, String> {
+        self.get("snapshot", hash)
+    }
+
+    fn set_page_text(&self, id: i32, text: &str) -> Result<(), String> {
+        self.post::<_, i32>("set_page_text", &(id, text))?;
+        Ok(())
+    }
+
+    fn search(&self, query: &str) -> Result<Vec<SearchResult>, String> {
+        self.get("search", query)
+    }
+
+    fn visit(&self, id: i32) -> Result<(), String> {
+        self.post::<_, i32>("visit", &id)?;
+        Ok(())
+    }
+
+    fn visits(&self) -> Result<Vec<Visits>, String> {
+        Self::send(self.client.get(self.endpoint("visits")))
+    }
+
+    fn tags(&self) -> Result<Vec<TagCount>, String> {
+        Self::send(self.client.get(self.endpoint("tags")))
+    }
 }
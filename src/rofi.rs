@@ -16,6 +16,7 @@
 
 pub mod pango;
 
+use std::collections::HashMap;
 use std::io::{Read, Write};
 use std::process::{Child, Command, Stdio};
 use thiserror::Error;
@@ -39,6 +40,7 @@ where
     format: Format,
     args: Vec<String>,
     sort: bool,
+    row_options: HashMap<usize, Vec<(String, String)>>,
 }
 
 /// Rofi child process.
@@ -86,8 +88,9 @@ impl RofiChild<String> {
 }
 
 impl RofiChild<usize> {
-    /// Wait for the result and return the output as an usize.
-    fn wait_with_output(&mut self) -> Result<(i32, Option<usize>), Error> {
+    /// Wait for the result and return the output as the indices of the
+    /// selected elements, several with multi-select.
+    fn wait_with_output(&mut self) -> Result<(i32, Vec<usize>), Error> {
         let status = self.p.wait()?;
         let code = status.code().unwrap();
         if status.success() || (10..=30).contains(&code) {
@@ -95,19 +98,17 @@ impl RofiChild<usize> {
             if let Some(mut reader) = self.p.stdout.take() {
                 reader.read_to_string(&mut buffer)?;
             }
-            if buffer.ends_with('\n') {
-                buffer.pop();
+            if buffer.trim().is_empty() {
+                return Err(Error::Blank {});
             }
-            if buffer.is_empty() {
-                Err(Error::Blank {})
-            } else {
-                let idx: isize = buffer.parse::<isize>()?;
-                if idx < 0 || idx > self.num_elements as isize {
-                    Ok((code, None))
-                } else {
-                    Ok((code, Some(idx as usize)))
+            let mut indices = vec![];
+            for line in buffer.lines() {
+                let idx: isize = line.parse::<isize>()?;
+                if idx >= 0 && idx < self.num_elements as isize {
+                    indices.push(idx as usize);
                 }
             }
+            Ok((code, indices))
         } else {
             Err(Error::Interrupted {})
         }
@@ -129,6 +130,7 @@ where
             args: Vec::new(),
             sort: false,
             message: None,
+            row_options: HashMap::new(),
         }
     }
 
@@ -141,6 +143,13 @@ where
     /// show the window, and return the index of the selected string This
     /// function will overwrite any subsequent calls to `self.format`.
     pub fn run_index(&mut self) -> Result<(i32, Option<usize>), Error> {
+        let (code, indices) = self.spawn_index()?.wait_with_output()?;
+        Ok((code, indices.first().copied()))
+    }
+
+    /// Show the window, and return the indices of the selected strings. Only
+    /// returns more than one with `self.multi_select`.
+    pub fn run_indices(&mut self) -> Result<(i32, Vec<usize>), Error> {
         self.spawn_index()?.wait_with_output()
     }
 
@@ -156,6 +165,27 @@ where
         self
     }
 
+    /// enable selecting several rows with Shift+Return
+    pub fn multi_select(&mut self) -> &mut Self {
+        self.args.push("-multi-select".to_string());
+        self
+    }
+
+    /// Set option `key` of the row at `index`, like its `icon` or `meta`
+    pub fn row_option(&mut self, index: usize, key: &str, value: impl Into<String>) -> &mut Self {
+        self.row_options
+            .entry(index)
+            .or_default()
+            .push((key.to_string(), value.into()));
+        self
+    }
+
+    /// show the `icon` row options
+    pub fn show_icons(&mut self) -> &mut Self {
+        self.args.push("-show-icons".to_string());
+        self
+    }
+
     /// enable password mode
     pub fn password(&mut self) -> &mut Self {
         self.args.push("-password".to_string());
@@ -199,7 +229,7 @@ where
         self
     }
 
-    ///
+    /// Set the message shown below the prompt
     pub fn message(&mut self, message: impl Into<String>) -> &mut Self {
         self.args.push("-mesg".to_string());
         self.args.push(message.into());
@@ -224,7 +254,7 @@ where
         self
     }
 
-    ///
+    /// Bind a custom keyboard shortcut, reported as exit code `9 + id`
     pub fn kb_custom(&mut self, id: u32, shortcut: &str) -> &mut Self {
         self.args.push(format!("-kb-custom-{}", id));
         self.args.push(shortcut.to_string());
@@ -284,8 +314,16 @@ where
             .spawn()?;
 
         if let Some(mut writer) = child.stdin.take() {
-            for element in self.elements {
+            for (index, element) in self.elements.iter().enumerate() {
                 writer.write_all(element.as_ref().as_bytes())?;
+                if let Some(options) = self.row_options.get(&index) {
+                    let options = options
+                        .iter()
+                        .map(|(key, value)| format!("{}\x1f{}", key, value))
+                        .collect::<Vec<String>>()
+                        .join("\x1f");
+                    writer.write_all(format!("\0{}", options).as_bytes())?;
+                }
                 writer.write_all(b"\n")?;
             }
         }
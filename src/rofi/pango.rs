@@ -27,7 +27,7 @@ pub struct Pango<'a> {
 
 impl<'a> Pango<'a> {
     /// Generate a new pango class
-    pub fn new(content: &'a str) -> Pango<'_> {
+    pub fn new(content: &'a str) -> Pango<'a> {
         Pango {
             content,
             options: HashMap::new(),
@@ -35,7 +35,7 @@ impl<'a> Pango<'a> {
     }
 
     /// Generate a new pango class with options capacity
-    pub fn with_capacity(content: &'a str, size: usize) -> Pango<'_> {
+    pub fn with_capacity(content: &'a str, size: usize) -> Pango<'a> {
         Pango {
             content,
             options: HashMap::with_capacity(size),
@@ -216,6 +216,69 @@ impl<'a> Pango<'a> {
     }
 }
 
+/// Escape `text` so it is shown as is inside markup
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '\'' => escaped.push_str("&apos;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Plain text of `markup`, without tags and with entities decoded
+pub fn strip(markup: &str) -> String {
+    let mut text = String::with_capacity(markup.len());
+    let mut rest = markup;
+
+    while let Some(c) = rest.chars().next() {
+        match c {
+            '<' => match rest.find('>') {
+                Some(end) => rest = &rest[end + 1..],
+                None => {
+                    text.push_str(rest);
+                    rest = "";
+                }
+            },
+            '&' => {
+                let entity = rest.find(';').and_then(|end| {
+                    let c = match &rest[1..end] {
+                        "amp" => '&',
+                        "lt" => '<',
+                        "gt" => '>',
+                        "apos" | "#39" => '\'',
+                        "quot" => '"',
+                        _ => return None,
+                    };
+                    Some((end, c))
+                });
+                match entity {
+                    Some((end, c)) => {
+                        text.push(c);
+                        rest = &rest[end + 1..];
+                    }
+                    None => {
+                        text.push('&');
+                        rest = &rest[1..];
+                    }
+                }
+            }
+            c => {
+                text.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+
+    text
+}
+
 /// Enumeration over all available font families
 #[derive(Debug, Clone, Copy)]
 pub enum FontFamily {
@@ -11,77 +11,684 @@
 // You should have received a copy of the GNU General Public License along with this
 // program. If not, see <https://www.gnu.org/licenses/>.
 
-use copypasta::{ClipboardContext, ClipboardProvider};
+use itertools::Itertools;
+use url::Url;
 
-use crate::bookmark::Bookmark;
-use crate::bookmark_proxy::edit_bookmark;
-use crate::rofi;
+use crate::bookmark::{Bookmark, NewBookmark, TagCount};
+use crate::clipboard;
+use crate::config::{Config, RofiAction, RofiBinding, RofiConfig};
+use crate::editor::edit_bookmark;
+use crate::favicon::FaviconCache;
+use crate::frecency::sort_by_frecency;
+use crate::launcher::{launcher, Launcher, Menu, Selection};
+use crate::metadata;
+use crate::opener::{open_archived, open_bookmark};
+use crate::rofi::pango::{escape, Pango, Weight};
 use crate::BookmarkProxy;
 
-fn rofi_add(proxy: &dyn BookmarkProxy) -> Result<(), String> {
-    let mut ctx = ClipboardContext::new().map_err(|_| "Failed to create clipboard context")?;
-    let content = ctx
-        .get_contents()
-        .map_err(|_| "Failed to get clipboard contents")?;
-    let s = rofi::Rofi::new(&[content])
-        .prompt("URL")
-        .run()
-        .map_err(|_| "Adding bookmark aborted")?
-        .1
-        .unwrap();
+/// Replace the `{name}` placeholders of `template` with `value(name)`, leaving
+/// unknown ones as they are.
+fn expand(template: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
 
-    let v: Vec<String> = vec![];
-    let description = rofi::Rofi::new(&v)
-        .prompt("Description")
-        .run()
-        .map_err(|_| "Adding description aborted")?
-        .1
-        .unwrap();
-    proxy.add(&s, &description, vec![])
+    while let Some(start) = rest.find('{') {
+        expanded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        match rest
+            .find('}')
+            .and_then(|end| Some((end, value(&rest[1..end])?)))
+        {
+            Some((end, value)) => {
+                expanded.push_str(&value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                expanded.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Pango markup of the row showing `bookmark`.
+pub(crate) fn rofi_row(config: &RofiConfig, template: &str, bookmark: &Bookmark) -> String {
+    let url = &bookmark.bookmark.url;
+    let description = if bookmark.bookmark.description.is_empty() {
+        url
+    } else {
+        &bookmark.bookmark.description
+    };
+    let domain = Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| host.to_string()))
+        .unwrap_or_default();
+    let tags = bookmark
+        .tags
+        .iter()
+        .map(|tag| format!("#{}", tag.tag))
+        .collect::<Vec<String>>()
+        .join(" ");
+    let tag_color = config.get_tag_color();
+
+    expand(template, |name| match name {
+        "description" => Some(
+            Pango::new(&escape(description))
+                .weight(Weight::Bold)
+                .build(),
+        ),
+        "domain" => Some(Pango::new(&escape(&domain)).alpha("50%").build()),
+        "url" => Some(escape(url)),
+        "tags" if tags.is_empty() => Some(String::new()),
+        "tags" => Some(Pango::new(&escape(&tags)).fg_color(&tag_color).build()),
+        "id" => Some(bookmark.bookmark.id.to_string()),
+        _ => None,
+    })
+}
+
+/// Paths of the cached favicons of `bookmarks`, for the `icon` row option.
+/// Missing ones are downloaded in the background for the next menu.
+pub(crate) fn row_icons(config: &RofiConfig, bookmarks: &[Bookmark]) -> Vec<Option<String>> {
+    let cache = match FaviconCache::new() {
+        Some(cache) => cache,
+        None => return vec![None; bookmarks.len()],
+    };
+    if config.get_fetch_icons() {
+        let _ = cache.fetch_in_background(bookmarks);
+    }
+
+    cache
+        .icons(bookmarks)
+        .into_iter()
+        .map(|icon| Some(icon?.to_string_lossy().to_string()))
+        .collect()
+}
+
+/// Ask for tags until Escape, completing from the `known` ones. Each entry can
+/// hold several tags separated by spaces.
+fn rofi_tags(
+    launcher: &dyn Launcher,
+    prompt: &str,
+    known: &[String],
+) -> Result<Vec<String>, String> {
+    let mut tags: Vec<String> = vec![];
+
+    loop {
+        let rows: Vec<String> = known
+            .iter()
+            .filter(|tag| !tags.contains(tag))
+            .map(|tag| escape(tag))
+            .collect();
+        let message = if tags.is_empty() {
+            "<b>Enter</b>: Add tag <b>Escape</b>: Done".to_string()
+        } else {
+            format!(
+                "{} <b>Enter</b>: Add tag <b>Escape</b>: Done",
+                escape(&tags.join(" "))
+            )
+        };
+
+        let entry = match launcher.show(&Menu {
+            rows: &rows,
+            prompt,
+            message: Some(message),
+            input: true,
+            ..Default::default()
+        })? {
+            Some(Selection {
+                key: None, text, ..
+            }) => text,
+            _ => return Ok(tags),
+        };
+        for tag in entry.split_whitespace() {
+            if !tags.iter().any(|t| t == tag) {
+                tags.push(tag.to_string());
+            }
+        }
+    }
+}
+
+/// Text entered in a menu offering `suggestions`, none if dismissed.
+fn rofi_input(
+    launcher: &dyn Launcher,
+    prompt: &str,
+    suggestions: &[String],
+) -> Result<Option<String>, String> {
+    let rows: Vec<String> = suggestions.iter().map(|row| escape(row)).collect();
+    let selection = launcher.show(&Menu {
+        rows: &rows,
+        prompt,
+        input: true,
+        ..Default::default()
+    })?;
+    Ok(selection
+        .map(|selection| selection.text)
+        .filter(|text| !text.is_empty()))
+}
+
+fn rofi_add(
+    launcher: &dyn Launcher,
+    proxy: &dyn BookmarkProxy,
+    fetch_metadata: bool,
+) -> Result<(), String> {
+    let urls: Vec<String> = clipboard::url().into_iter().collect();
+    let s = rofi_input(launcher, "URL", &urls)?.ok_or("Adding bookmark aborted")?;
+
+    let metadata = if fetch_metadata {
+        metadata::fetch(&s).ok()
+    } else {
+        None
+    };
+    let titles: Vec<String> = metadata
+        .as_ref()
+        .and_then(|metadata| metadata.best_title())
+        .into_iter()
+        .collect();
+    let description =
+        rofi_input(launcher, "Description", &titles)?.ok_or("Adding description aborted")?;
+    let known: Vec<String> = proxy.tags()?.into_iter().map(|tag| tag.tag).collect();
+    let tags = rofi_tags(launcher, "Tags", &known)?;
+
+    proxy.add(&NewBookmark {
+        url: s,
+        description,
+        tags,
+        metadata,
+    })?;
+    Ok(())
+}
+
+fn rofi_confirm(launcher: &dyn Launcher, message: &str) -> Result<bool, String> {
+    let options = ["No".to_string(), "Yes".to_string()];
+    let selection = launcher.show(&Menu {
+        rows: &options,
+        prompt: message,
+        ..Default::default()
+    })?;
+    Ok(matches!(
+        selection,
+        Some(Selection { key: None, rows, .. }) if rows == [1]
+    ))
 }
 
 fn rofi_delete(
+    launcher: &dyn Launcher,
     proxy: &dyn BookmarkProxy,
-    index: usize,
-    books: Vec<Bookmark>,
+    bookmarks: &[&Bookmark],
 ) -> Result<(), String> {
-    proxy.delete(books[index].bookmark.id)
+    let question = match bookmarks {
+        [bookmark] => format!("Delete {}?", bookmark.bookmark.url),
+        _ => format!("Delete {} bookmarks?", bookmarks.len()),
+    };
+    if rofi_confirm(launcher, &question)? {
+        for bookmark in bookmarks {
+            proxy.delete(bookmark.bookmark.id)?;
+        }
+    }
+    Ok(())
+}
+
+fn rofi_edit(proxy: &dyn BookmarkProxy, bookmarks: &[&Bookmark]) -> Result<(), String> {
+    for bookmark in bookmarks {
+        edit_bookmark(proxy, bookmark.bookmark.id, true)?;
+    }
+    Ok(())
 }
 
-fn rofi_edit(proxy: &dyn BookmarkProxy, index: usize, books: Vec<Bookmark>) -> Result<(), String> {
-    edit_bookmark(proxy, books[index].bookmark.id, Some(true));
+/// Add the tags picked from the existing ones, or typed, to `bookmarks`.
+fn rofi_tag(
+    launcher: &dyn Launcher,
+    proxy: &dyn BookmarkProxy,
+    bookmarks: &[&Bookmark],
+) -> Result<(), String> {
+    let known: Vec<String> = proxy.tags()?.into_iter().map(|tag| tag.tag).collect();
+    let added = rofi_tags(launcher, "Add tags", &known)?;
+    if added.is_empty() {
+        return Ok(());
+    }
+
+    for bookmark in bookmarks {
+        let mut tags = bookmark.tag_names();
+        for tag in &added {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+        proxy.update_tags(bookmark.bookmark.id, &tags)?;
+    }
     Ok(())
 }
 
-fn rofi_open(url: &str) -> Result<(), String> {
-    open::with(url, "firefox").map_err(|_| "Failed to open url")?;
+/// Remove the tags picked from the ones of `bookmarks`.
+fn rofi_untag(
+    launcher: &dyn Launcher,
+    proxy: &dyn BookmarkProxy,
+    bookmarks: &[&Bookmark],
+) -> Result<(), String> {
+    let known: Vec<String> = bookmarks
+        .iter()
+        .flat_map(|bookmark| bookmark.tag_names())
+        .sorted()
+        .dedup()
+        .collect();
+    let removed = rofi_tags(launcher, "Remove tags", &known)?;
+
+    for bookmark in bookmarks {
+        let tags = bookmark.tag_names();
+        if tags.iter().any(|tag| removed.contains(tag)) {
+            let kept: Vec<String> = tags
+                .into_iter()
+                .filter(|tag| !removed.contains(tag))
+                .collect();
+            proxy.update_tags(bookmark.bookmark.id, &kept)?;
+        }
+    }
     Ok(())
 }
 
-pub fn command_rofi(proxy: &dyn BookmarkProxy) -> Result<(), String> {
-    let bookmarks = proxy.bookmarks()?;
+pub(crate) fn rofi_open(
+    proxy: &dyn BookmarkProxy,
+    config: &Config,
+    bookmarks: &[&Bookmark],
+) -> Result<(), String> {
+    let open_config = config.open.clone().unwrap_or_default();
+    for bookmark in bookmarks {
+        open_bookmark(&open_config, bookmark)?;
+        proxy.visit(bookmark.bookmark.id)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn rofi_open_archived(
+    proxy: &dyn BookmarkProxy,
+    config: &Config,
+    bookmarks: &[&Bookmark],
+) -> Result<(), String> {
+    let open_config = config.open.clone().unwrap_or_default();
+    for bookmark in bookmarks {
+        open_archived(&open_config, proxy, bookmark.bookmark.id)?;
+    }
+    Ok(())
+}
+
+/// Markdown link to `bookmark`, titled with its description.
+pub(crate) fn markdown_link(bookmark: &Bookmark) -> String {
+    let url = &bookmark.bookmark.url;
+    let title = if bookmark.bookmark.description.is_empty() {
+        url
+    } else {
+        &bookmark.bookmark.description
+    };
+    format!(
+        "[{}]({})",
+        title.replace('[', "\\[").replace(']', "\\]"),
+        url.replace('(', "%28").replace(')', "%29")
+    )
+}
 
-    let books = bookmarks
+/// Help line listing the keybindings of `actions`.
+pub(crate) fn help_message(actions: &[RofiBinding]) -> String {
+    actions
         .iter()
-        .map(|bookmark| bookmark.bookmark.url.to_string())
+        .map(|binding| {
+            format!(
+                "<b>{}</b>: {}",
+                escape(&binding.key),
+                escape(binding.get_label())
+            )
+        })
+        .join(" ")
+}
+
+/// Action of the rofi exit `code`, none for codes no keybinding reports.
+pub(crate) fn action_for(code: i32, actions: &[RofiBinding]) -> Option<RofiAction> {
+    match code {
+        0 => Some(RofiAction::Open),
+        code => actions
+            .get(usize::try_from(code - 10).ok()?)
+            .map(|binding| binding.action),
+    }
+}
+
+/// Pango markup of the row showing `tag` in the tag menu.
+fn tag_row(config: &RofiConfig, tag: &TagCount) -> String {
+    format!(
+        "{}  {}",
+        Pango::new(&escape(&format!("#{}", tag.tag)))
+            .fg_color(&config.get_tag_color())
+            .build(),
+        Pango::new(&tag.count.to_string()).alpha("50%").build()
+    )
+}
+
+/// How the bookmark menu was left
+enum Exit {
+    Done,
+    Back,
+}
+
+/// Rofi reports custom keybindings as exit codes 10 to 28
+const MAX_BINDINGS: usize = 19;
+
+/// Action picked from a second menu, for launchers without custom keys.
+fn pick_action(
+    launcher: &dyn Launcher,
+    actions: &[RofiBinding],
+) -> Result<Option<RofiAction>, String> {
+    let rows: Vec<String> = std::iter::once(RofiAction::Open.label())
+        .chain(actions.iter().map(|binding| binding.get_label()))
+        .map(escape)
+        .collect();
+    let selection = launcher.show(&Menu {
+        rows: &rows,
+        prompt: "Action",
+        ..Default::default()
+    })?;
+
+    Ok(
+        match selection.and_then(|selection| selection.rows.first().copied()) {
+            Some(0) => Some(RofiAction::Open),
+            Some(index) => actions.get(index - 1).map(|binding| binding.action),
+            None => None,
+        },
+    )
+}
+
+/// Show `bookmarks` and run the action picked on the selected ones, several
+/// can be selected if the launcher allows it. With `back`, the `back` action
+/// leaves the menu without doing anything.
+fn rofi_bookmarks(
+    launcher: &dyn Launcher,
+    proxy: &dyn BookmarkProxy,
+    config: &Config,
+    mut bookmarks: Vec<Bookmark>,
+    prompt: &str,
+    back: bool,
+) -> Result<Exit, String> {
+    sort_by_frecency(&mut bookmarks, &proxy.visits()?);
+
+    let rofi_config = config.rofi.clone().unwrap_or_default();
+    let template = rofi_config.get_row();
+    let rows = bookmarks
+        .iter()
+        .map(|bookmark| rofi_row(&rofi_config, &template, bookmark))
         .collect::<Vec<String>>();
+    let icons = if rofi_config.get_icons() {
+        row_icons(&rofi_config, &bookmarks)
+    } else {
+        vec![]
+    };
 
-    let ret = rofi::Rofi::new(&books)
-        .kb_custom(1, "Alt+n")
-        .kb_custom(2, "Alt+d")
-        .kb_custom(3, "Alt+e")
-        .prompt("> ")
-        .message("<b>Alt+n</b>: Add new bookmark <b>Alt+d</b>: Delete bookmark <b>Alt+e</b>: Edit bookmark")
-        .run_index();
-
-    match ret {
-        Ok((10, _)) => rofi_add(proxy),
-        Ok((11, Some(index))) => rofi_delete(proxy, index, bookmarks),
-        Ok((12, Some(index))) => rofi_edit(proxy, index, bookmarks),
-        Ok((0, Some(index))) => rofi_open(&bookmarks[index].bookmark.url),
-        Err(_) => Ok(()),
-        _ => panic!(),
+    let actions: Vec<RofiBinding> = rofi_config
+        .get_actions()
+        .into_iter()
+        .filter(|binding| back || binding.action != RofiAction::Back)
+        .collect();
+    if actions.len() > MAX_BINDINGS {
+        return Err(format!(
+            "At most {} keybindings are supported",
+            MAX_BINDINGS
+        ));
+    }
+
+    let keys: Vec<String> = if launcher.supports_keys() {
+        actions.iter().map(|binding| binding.key.clone()).collect()
+    } else {
+        vec![]
+    };
+    let mut message = if keys.is_empty() {
+        String::new()
+    } else {
+        help_message(&actions)
+    };
+    if let Some(key) = launcher.multi_select_key() {
+        message.push_str(&format!(" <b>{}</b>: Select several", key));
+    }
+
+    let selection = match launcher.show(&Menu {
+        rows: &rows,
+        prompt,
+        message: Some(message.trim().to_string()).filter(|message| !message.is_empty()),
+        keys: &keys,
+        multi: true,
+        icons: &icons,
+        ..Default::default()
+    })? {
+        Some(selection) => selection,
+        None => return Ok(Exit::Done),
+    };
+    let selected: Vec<&Bookmark> = selection
+        .rows
+        .iter()
+        .map(|index| &bookmarks[*index])
+        .collect();
+    let action = match selection.key {
+        None if keys.is_empty() && !selected.is_empty() => match pick_action(launcher, &actions)? {
+            Some(action) => action,
+            None => return Ok(Exit::Done),
+        },
+        None => RofiAction::Open,
+        Some(index) => actions
+            .get(index)
+            .map(|binding| binding.action)
+            .ok_or(format!("Unexpected keybinding {}", index + 1))?,
+    };
+
+    match action {
+        RofiAction::Add => rofi_add(launcher, proxy, config.get_fetch_metadata()),
+        RofiAction::Back => return Ok(Exit::Back),
+        _ if selected.is_empty() => Ok(()),
+        RofiAction::Open => rofi_open(proxy, config, &selected),
+        RofiAction::CopyUrl => clipboard::copy(
+            selected
+                .iter()
+                .map(|bookmark| bookmark.bookmark.url.clone())
+                .join("\n"),
+        ),
+        RofiAction::CopyMarkdown => clipboard::copy(
+            selected
+                .iter()
+                .map(|bookmark| markdown_link(bookmark))
+                .join("\n"),
+        ),
+        RofiAction::Edit => rofi_edit(proxy, &selected),
+        RofiAction::Delete => rofi_delete(launcher, proxy, &selected),
+        RofiAction::Tag => rofi_tag(launcher, proxy, &selected),
+        RofiAction::Untag => rofi_untag(launcher, proxy, &selected),
+        RofiAction::OpenArchived => rofi_open_archived(proxy, config, &selected),
     }?;
 
+    Ok(Exit::Done)
+}
+
+/// Show the tags with their counts, then the bookmarks under the chosen one,
+/// until a bookmark action runs or the menus are dismissed.
+fn rofi_by_tag(
+    launcher: &dyn Launcher,
+    proxy: &dyn BookmarkProxy,
+    config: &Config,
+) -> Result<(), String> {
+    let rofi_config = config.rofi.clone().unwrap_or_default();
+
+    loop {
+        let bookmarks = proxy.bookmarks()?;
+        let tags = proxy.tags()?;
+        let rows = std::iter::once(format!(
+            "<i>All bookmarks</i>  {}",
+            Pango::new(&bookmarks.len().to_string())
+                .alpha("50%")
+                .build()
+        ))
+        .chain(tags.iter().map(|tag| tag_row(&rofi_config, tag)))
+        .collect::<Vec<String>>();
+
+        let selection = launcher.show(&Menu {
+            rows: &rows,
+            prompt: "Tag",
+            ..Default::default()
+        })?;
+        let (bookmarks, prompt) =
+            match selection.and_then(|selection| selection.rows.first().copied()) {
+                Some(0) => (bookmarks, "> ".to_string()),
+                Some(index) => {
+                    let tag = &tags[index - 1].tag;
+                    let tagged = bookmarks
+                        .into_iter()
+                        .filter(|bookmark| bookmark.tags.iter().any(|t| &t.tag == tag))
+                        .collect();
+                    (tagged, format!("#{}", tag))
+                }
+                None => return Ok(()),
+            };
+
+        if let Exit::Done = rofi_bookmarks(launcher, proxy, config, bookmarks, &prompt, true)? {
+            return Ok(());
+        }
+    }
+}
+
+/// Bookmark menus shown by the launcher chosen in `config`.
+pub fn command_rofi(
+    proxy: &dyn BookmarkProxy,
+    config: &Config,
+    by_tag: bool,
+) -> Result<(), String> {
+    let launcher = launcher(config.get_launcher());
+    if by_tag {
+        return rofi_by_tag(&*launcher, proxy, config);
+    }
+
+    rofi_bookmarks(&*launcher, proxy, config, proxy.bookmarks()?, "> ", false)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::models::{Bookmarks, Tags};
+
+    fn bookmark(description: &str, url: &str, tags: &[&str]) -> Bookmark {
+        Bookmark::new(
+            &Bookmarks {
+                id: 7,
+                url: url.to_string(),
+                description: description.to_string(),
+                deleted_at: None,
+                metadata: None,
+            },
+            &tags
+                .iter()
+                .enumerate()
+                .map(|(id, tag)| Tags {
+                    id: id as i32,
+                    tag: tag.to_string(),
+                })
+                .collect::<Vec<Tags>>(),
+        )
+    }
+
+    #[test]
+    fn placeholders_are_expanded_once() {
+        let value = |name: &str| match name {
+            "a" => Some("{b}".to_string()),
+            "b" => Some("B".to_string()),
+            _ => None,
+        };
+
+        assert_eq!(expand("{a} {b} {c} {", value), "{b} B {c} {");
+        assert_eq!(expand("}{b}{{b}}", value), "}B{B}");
+    }
+
+    #[test]
+    fn rows_are_escaped_pango() {
+        let config = RofiConfig {
+            tag_color: Some("red".to_string()),
+            ..Default::default()
+        };
+        let rust = bookmark(
+            "Rust & <Cargo>",
+            "https://www.rust-lang.org/learn",
+            &["lang", "rust"],
+        );
+
+        assert_eq!(
+            rofi_row(&config, &config.get_row(), &rust),
+            "<span weight='bold'>Rust &amp; &lt;Cargo&gt;</span>  \
+             <span alpha='50%'>www.rust-lang.org</span>  \
+             <span foreground='red'>#lang #rust</span>"
+        );
+        assert_eq!(
+            rofi_row(
+                &config,
+                "{id}. <i>{url}</i> {tags}",
+                &bookmark("", "https://a.com/?a&b", &[])
+            ),
+            "7. <i>https://a.com/?a&amp;b</i> "
+        );
+        assert_eq!(
+            rofi_row(
+                &config,
+                "{description}",
+                &bookmark("", "https://a.com", &[])
+            ),
+            "<span weight='bold'>https://a.com</span>"
+        );
+    }
+
+    #[test]
+    fn tag_rows_show_counts() {
+        let tag = TagCount {
+            tag: "c&c".to_string(),
+            count: 3,
+        };
+
+        assert_eq!(
+            tag_row(&RofiConfig::default(), &tag),
+            "<span foreground='#5e81ac'>#c&amp;c</span>  <span alpha='50%'>3</span>"
+        );
+    }
+
+    #[test]
+    fn markdown_links_are_escaped() {
+        assert_eq!(
+            markdown_link(&bookmark("[RFC] Rust", "https://a.com/x_(y)", &[])),
+            "[\\[RFC\\] Rust](https://a.com/x_%28y%29)"
+        );
+        assert_eq!(
+            markdown_link(&bookmark("", "https://a.com", &[])),
+            "[https://a.com](https://a.com)"
+        );
+    }
+
+    #[test]
+    fn actions_are_configurable() {
+        let config: RofiConfig = toml::from_str(
+            r#"
+            actions = [
+                { key = "Alt+y", action = "copy_url" },
+                { key = "Alt+<", label = "Offline", action = "open_archived" },
+            ]
+            "#,
+        )
+        .unwrap();
+        let actions = config.get_actions();
+
+        assert_eq!(
+            help_message(&actions),
+            "<b>Alt+y</b>: Copy url <b>Alt+&lt;</b>: Offline"
+        );
+        assert_eq!(action_for(0, &actions), Some(RofiAction::Open));
+        assert_eq!(action_for(10, &actions), Some(RofiAction::CopyUrl));
+        assert_eq!(action_for(11, &actions), Some(RofiAction::OpenArchived));
+        assert_eq!(action_for(12, &actions), None);
+        assert_eq!(action_for(5, &actions), None);
+        assert!(toml::from_str::<RofiConfig>(
+            r#"actions = [{ key = "Alt+x", action = "explode" }]"#
+        )
+        .is_err());
+    }
+}
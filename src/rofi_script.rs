@@ -0,0 +1,401 @@
+// Copyright 2024 João Freitas
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of
+// the GNU General Public License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS FOR
+// A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Rofi script mode, run by `rofi -modi 'marcador:marcador rofi-script'`.
+//!
+//! Rofi runs the script to get the rows, then again each time a row is picked
+//! with `ROFI_RETV` telling how, `ROFI_INFO` holding the info of the row and the
+//! menu being shown passed back in `ROFI_DATA`. Printing no rows closes rofi.
+
+use itertools::Itertools;
+
+use std::fmt;
+
+use crate::bookmark::{Bookmark, NewBookmark};
+use crate::bookmark_proxy::BookmarkProxy;
+use crate::clipboard;
+use crate::config::{Config, RofiAction, RofiBinding};
+use crate::frecency::sort_by_frecency;
+use crate::metadata;
+use crate::rofi::pango::escape;
+use crate::rofi_interface::{
+    action_for, help_message, markdown_link, rofi_open, rofi_open_archived, rofi_row, row_icons,
+};
+
+/// Menu shown by the script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum View {
+    Bookmarks,
+    ConfirmDelete(i32),
+    AddTags(i32),
+    RemoveTags(i32),
+}
+
+impl View {
+    /// View saved in `ROFI_DATA`, the bookmarks if unknown
+    pub fn parse(data: &str) -> View {
+        let (name, id) = match data.split_once(':') {
+            Some((name, id)) => (name, id.parse().ok()),
+            None => (data, None),
+        };
+        match (name, id) {
+            ("delete", Some(id)) => View::ConfirmDelete(id),
+            ("tag", Some(id)) => View::AddTags(id),
+            ("untag", Some(id)) => View::RemoveTags(id),
+            _ => View::Bookmarks,
+        }
+    }
+
+    fn data(&self) -> String {
+        match self {
+            View::Bookmarks => "bookmarks".to_string(),
+            View::ConfirmDelete(id) => format!("delete:{}", id),
+            View::AddTags(id) => format!("tag:{}", id),
+            View::RemoveTags(id) => format!("untag:{}", id),
+        }
+    }
+}
+
+/// Row of a menu, `info` is passed back when it is picked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Row {
+    pub text: String,
+    pub info: String,
+    /// Extra text matched when filtering
+    pub meta: Option<String>,
+    /// Path of the icon, shown when rofi runs with `-show-icons`
+    pub icon: Option<String>,
+}
+
+/// Output of the script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Menu {
+    pub view: View,
+    pub prompt: String,
+    pub message: Option<String>,
+    /// Whether text that matches no row can be entered
+    pub custom: bool,
+    pub rows: Vec<Row>,
+}
+
+/// Rofi reads one row per line and separates row options with `\0` and `\x1f`
+fn clean(text: &str) -> String {
+    text.replace(['\n', '\0', '\x1f'], " ")
+}
+
+impl fmt::Display for Menu {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "\0prompt\x1f{}", clean(&self.prompt))?;
+        if let Some(message) = &self.message {
+            writeln!(f, "\0message\x1f{}", clean(message))?;
+        }
+        writeln!(f, "\0markup-rows\x1ftrue")?;
+        writeln!(f, "\0use-hot-keys\x1ftrue")?;
+        writeln!(f, "\0no-custom\x1f{}", !self.custom)?;
+        writeln!(f, "\0data\x1f{}", self.view.data())?;
+
+        for row in &self.rows {
+            write!(f, "{}\0info\x1f{}", clean(&row.text), clean(&row.info))?;
+            if let Some(meta) = &row.meta {
+                write!(f, "\x1fmeta\x1f{}", clean(meta))?;
+            }
+            if let Some(icon) = &row.icon {
+                write!(f, "\x1ficon\x1f{}", clean(icon))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// How rofi ran the script
+#[derive(Debug, Clone)]
+pub struct Request {
+    /// `ROFI_RETV`: 0 on start, 1 for a row, 2 for custom text and 10 to 28
+    /// for the custom keybindings
+    pub retv: i32,
+    /// Text of the picked row or the custom text
+    pub selection: Option<String>,
+    /// `ROFI_INFO` of the picked row
+    pub info: Option<String>,
+    pub view: View,
+}
+
+impl Request {
+    fn from_env(selection: Option<String>) -> Request {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        Request {
+            retv: var("ROFI_RETV")
+                .and_then(|retv| retv.parse().ok())
+                .unwrap_or(0),
+            selection,
+            info: var("ROFI_INFO"),
+            view: var("ROFI_DATA")
+                .map(|data| View::parse(&data))
+                .unwrap_or(View::Bookmarks),
+        }
+    }
+
+    /// Id of the bookmark in the picked row
+    fn id(&self) -> Option<i32> {
+        self.info.as_ref()?.parse().ok()
+    }
+}
+
+/// Keybindings of the actions table, rofi decides the keys
+fn actions(config: &Config) -> Vec<RofiBinding> {
+    config.rofi.clone().unwrap_or_default().get_actions()
+}
+
+/// Whether `action` can run in script mode. There are no tags to go back to,
+/// and an editor would write into rofi's pipe or wait for rofi's keyboard grab.
+fn supported(action: RofiAction) -> bool {
+    !matches!(action, RofiAction::Back | RofiAction::Edit)
+}
+
+fn bookmarks_menu(
+    proxy: &dyn BookmarkProxy,
+    config: &Config,
+    message: Option<String>,
+) -> Result<Option<Menu>, String> {
+    let mut bookmarks = proxy.bookmarks()?;
+    sort_by_frecency(&mut bookmarks, &proxy.visits()?);
+
+    let rofi_config = config.rofi.clone().unwrap_or_default();
+    let template = rofi_config.get_row();
+    let icons = if rofi_config.get_icons() {
+        row_icons(&rofi_config, &bookmarks)
+    } else {
+        vec![None; bookmarks.len()]
+    };
+    let actions: Vec<RofiBinding> = actions(config)
+        .into_iter()
+        .filter(|binding| supported(binding.action))
+        .collect();
+    let help = format!(
+        "{} <b>Return</b>: Open, or add the typed url",
+        help_message(&actions)
+    );
+
+    Ok(Some(Menu {
+        view: View::Bookmarks,
+        prompt: "Bookmarks".to_string(),
+        message: Some(match message {
+            Some(message) => format!("<i>{}</i>  {}", escape(&message), help),
+            None => help,
+        }),
+        custom: true,
+        rows: bookmarks
+            .iter()
+            .zip(icons)
+            .map(|(bookmark, icon)| Row {
+                text: rofi_row(&rofi_config, &template, bookmark),
+                info: bookmark.bookmark.id.to_string(),
+                meta: Some(bookmark.bookmark.url.clone()),
+                icon,
+            })
+            .collect(),
+    }))
+}
+
+fn tags_menu(view: View, prompt: &str, message: String, tags: Vec<String>) -> Option<Menu> {
+    Some(Menu {
+        custom: matches!(view, View::AddTags(_)),
+        view,
+        prompt: prompt.to_string(),
+        message: Some(escape(&message)),
+        rows: tags
+            .into_iter()
+            .map(|tag| Row {
+                text: escape(&format!("#{}", tag)),
+                info: tag,
+                meta: None,
+                icon: None,
+            })
+            .collect(),
+    })
+}
+
+/// Add the bookmark typed in the filter, if it is an url.
+fn add_typed(proxy: &dyn BookmarkProxy, config: &Config, text: &str) -> Result<String, String> {
+    let url = match clipboard::as_url(text) {
+        Some(url) => url,
+        None => return Ok(format!("Not an url: {}", text)),
+    };
+    let metadata = if config.get_fetch_metadata() {
+        metadata::fetch(&url).ok()
+    } else {
+        None
+    };
+
+    let bookmark = proxy.add(&NewBookmark {
+        url,
+        description: metadata
+            .as_ref()
+            .and_then(|metadata| metadata.best_title())
+            .unwrap_or_default(),
+        tags: vec![],
+        metadata,
+    })?;
+    Ok(format!("Added {}", bookmark.bookmark.url))
+}
+
+/// Run the action bound to keybinding `retv` on `bookmark`.
+fn run_action(
+    proxy: &dyn BookmarkProxy,
+    config: &Config,
+    retv: i32,
+    bookmark: Option<Bookmark>,
+) -> Result<Option<Menu>, String> {
+    let action = match action_for(retv, &actions(config)) {
+        Some(action) if supported(action) => action,
+        Some(action) => {
+            let message = format!("{} is not available in script mode", action.label());
+            return bookmarks_menu(proxy, config, Some(message));
+        }
+        None => {
+            let message = format!("No action for keybinding {}", retv - 9);
+            return bookmarks_menu(proxy, config, Some(message));
+        }
+    };
+    let bookmark = match (action, bookmark) {
+        (RofiAction::Add, _) => {
+            let message = "Type the url and press Return".to_string();
+            return bookmarks_menu(proxy, config, Some(message));
+        }
+        (_, Some(bookmark)) => bookmark,
+        (_, None) => return bookmarks_menu(proxy, config, None),
+    };
+    let id = bookmark.bookmark.id;
+
+    match action {
+        RofiAction::Open => rofi_open(proxy, config, &[&bookmark])?,
+        RofiAction::CopyUrl => clipboard::copy(bookmark.bookmark.url.clone())?,
+        RofiAction::CopyMarkdown => clipboard::copy(markdown_link(&bookmark))?,
+        RofiAction::OpenArchived => rofi_open_archived(proxy, config, &[&bookmark])?,
+        RofiAction::Delete => {
+            return Ok(Some(Menu {
+                view: View::ConfirmDelete(id),
+                prompt: format!("Delete {}?", bookmark.bookmark.url),
+                message: None,
+                custom: false,
+                rows: ["No", "Yes"]
+                    .iter()
+                    .map(|answer| Row {
+                        text: answer.to_string(),
+                        info: answer.to_lowercase(),
+                        meta: None,
+                        icon: None,
+                    })
+                    .collect(),
+            }));
+        }
+        RofiAction::Tag => {
+            let own = bookmark.tag_names();
+            let known = proxy
+                .tags()?
+                .into_iter()
+                .map(|tag| tag.tag)
+                .filter(|tag| !own.contains(tag))
+                .collect();
+            let message = format!("Tags for {}, separated by spaces", bookmark.bookmark.url);
+            return Ok(tags_menu(View::AddTags(id), "Add tags", message, known));
+        }
+        RofiAction::Untag => {
+            let message = format!("Tags of {}", bookmark.bookmark.url);
+            let own = bookmark.tag_names();
+            return Ok(tags_menu(View::RemoveTags(id), "Remove tag", message, own));
+        }
+        RofiAction::Add | RofiAction::Edit | RofiAction::Back => {
+            return bookmarks_menu(proxy, config, None)
+        }
+    }
+
+    Ok(None)
+}
+
+/// Menu to print for `request`, none to close rofi.
+pub fn respond(
+    proxy: &dyn BookmarkProxy,
+    config: &Config,
+    request: &Request,
+) -> Result<Option<Menu>, String> {
+    let bookmark = match request.id() {
+        Some(id) if request.view == View::Bookmarks => proxy.bookmark(id).ok(),
+        _ => None,
+    };
+
+    match (&request.view, request.retv) {
+        (_, 0) => bookmarks_menu(proxy, config, None),
+        (View::Bookmarks, 1) => match bookmark {
+            Some(bookmark) => {
+                rofi_open(proxy, config, &[&bookmark])?;
+                Ok(None)
+            }
+            None => bookmarks_menu(proxy, config, None),
+        },
+        (View::Bookmarks, 2) => {
+            let text = request.selection.clone().unwrap_or_default();
+            let message = add_typed(proxy, config, &text)?;
+            bookmarks_menu(proxy, config, Some(message))
+        }
+        (View::Bookmarks, retv) if retv >= 10 => run_action(proxy, config, retv, bookmark),
+        (View::ConfirmDelete(id), 1) if request.info.as_deref() == Some("yes") => {
+            let url = proxy.bookmark(*id)?.bookmark.url;
+            proxy.delete(*id)?;
+            bookmarks_menu(proxy, config, Some(format!("Deleted {}", url)))
+        }
+        (View::AddTags(id), 1 | 2) => {
+            let entry = match request.retv {
+                1 => request.info.clone(),
+                _ => request.selection.clone(),
+            };
+            let mut tags = proxy.bookmark(*id)?.tag_names();
+            for tag in entry.iter().flat_map(|entry| entry.split_whitespace()) {
+                if !tags.iter().any(|t| t == tag) {
+                    tags.push(tag.to_string());
+                }
+            }
+            proxy.update_tags(*id, &tags)?;
+            let message = format!(
+                "Tagged {}",
+                tags.iter().map(|tag| format!("#{}", tag)).join(" ")
+            );
+            bookmarks_menu(proxy, config, Some(message))
+        }
+        (View::RemoveTags(id), 1) => {
+            let removed = request.info.clone().unwrap_or_default();
+            let tags: Vec<String> = proxy
+                .bookmark(*id)?
+                .tag_names()
+                .into_iter()
+                .filter(|tag| *tag != removed)
+                .collect();
+            proxy.update_tags(*id, &tags)?;
+            bookmarks_menu(proxy, config, Some(format!("Removed #{}", removed)))
+        }
+        _ => bookmarks_menu(proxy, config, None),
+    }
+}
+
+/// Answer one run of rofi in script mode, `selection` is the picked row.
+pub fn command_rofi_script(
+    proxy: &dyn BookmarkProxy,
+    config: &Config,
+    selection: Option<String>,
+) -> Result<(), String> {
+    if let Some(menu) = respond(proxy, config, &Request::from_env(selection))? {
+        print!("{}", menu);
+    }
+    Ok(())
+}
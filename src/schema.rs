@@ -5,6 +5,9 @@ diesel::table! {
         id -> Integer,
         url -> Text,
         description -> Text,
+        url_key -> Text,
+        deleted_at -> Nullable<BigInt>,
+        metadata -> Nullable<Text>,
     }
 }
 
@@ -16,6 +19,48 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    link_checks (bookmark_id) {
+        bookmark_id -> Integer,
+        url -> Text,
+        status -> Nullable<Integer>,
+        redirect -> Nullable<Text>,
+        error -> Nullable<Text>,
+        checked_at -> BigInt,
+    }
+}
+
+diesel::table! {
+    revisions (id) {
+        id -> Integer,
+        bookmark_id -> Integer,
+        rev -> Integer,
+        action -> Text,
+        url -> Text,
+        description -> Text,
+        tags -> Text,
+        created_at -> BigInt,
+    }
+}
+
+diesel::table! {
+    settings (key) {
+        key -> Text,
+        value -> Text,
+    }
+}
+
+diesel::table! {
+    snapshots (id) {
+        id -> Integer,
+        bookmark_id -> Integer,
+        hash -> Text,
+        url -> Text,
+        size -> BigInt,
+        created_at -> BigInt,
+    }
+}
+
 diesel::table! {
     tags (id) {
         id -> Integer,
@@ -23,7 +68,28 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    visits (id) {
+        id -> Integer,
+        bookmark_id -> Integer,
+        visited_at -> BigInt,
+    }
+}
+
 diesel::joinable!(bookmarks_tags -> bookmarks (bookmark_id));
 diesel::joinable!(bookmarks_tags -> tags (tag_id));
+diesel::joinable!(link_checks -> bookmarks (bookmark_id));
+diesel::joinable!(revisions -> bookmarks (bookmark_id));
+diesel::joinable!(snapshots -> bookmarks (bookmark_id));
+diesel::joinable!(visits -> bookmarks (bookmark_id));
 
-diesel::allow_tables_to_appear_in_same_query!(bookmarks, bookmarks_tags, tags,);
+diesel::allow_tables_to_appear_in_same_query!(
+    bookmarks,
+    bookmarks_tags,
+    link_checks,
+    revisions,
+    settings,
+    snapshots,
+    tags,
+    visits,
+);
@@ -11,14 +11,22 @@
 // You should have received a copy of the GNU General Public License along with this
 // program. If not, see <https://www.gnu.org/licenses/>.
 
-use actix_web::{web, App, HttpServer, Result};
+use actix_web::{error, web, App, HttpResponse, HttpServer, Result};
 use clap::Parser;
 
 use serde::{Deserialize, Serialize};
 
-use crate::bookmark::Bookmark;
+use std::net::TcpListener;
+use std::thread;
+use std::time::Duration;
 
-use crate::config::{Config, ServerConfig};
+use crate::bookmark::{
+    Bookmark, LinkCheck, NewBookmark, Revision, SearchResult, Snapshot, TagCount, Visits,
+};
+
+use crate::check::check;
+use crate::config::{CheckConfig, Config};
+use crate::metadata::PageMetadata;
 use crate::{BookmarkProxy, LocalProxy};
 
 #[derive(Parser)]
@@ -38,10 +46,18 @@ struct State {
     local_proxy: LocalProxy,
 }
 
+/// Largest json body accepted by the endpoints receiving archived pages, which
+/// inline every image, stylesheet and script of the page.
+const MAX_PAGE_PAYLOAD: usize = 256 * 1024 * 1024;
+
 #[derive(Serialize, Deserialize)]
 pub struct AddParams {
     pub url: String,
     pub description: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub metadata: Option<PageMetadata>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -49,30 +65,49 @@ pub struct DeleteParams {
     pub id: i32,
 }
 
-async fn endpoint_bookmark(state: web::Data<State>, info: web::Json<i32>) -> web::Json<Bookmark> {
-    web::Json(state.local_proxy.bookmark(info.0).unwrap())
+async fn endpoint_bookmark(
+    state: web::Data<State>,
+    info: web::Json<i32>,
+) -> Result<web::Json<Bookmark>> {
+    let bookmark = state
+        .local_proxy
+        .bookmark(info.0)
+        .map_err(error::ErrorNotFound)?;
+    Ok(web::Json(bookmark))
 }
 
-async fn endpoint_list(state: web::Data<State>) -> web::Json<Vec<Bookmark>> {
-    web::Json(state.local_proxy.bookmarks().unwrap())
+async fn endpoint_list(state: web::Data<State>) -> Result<web::Json<Vec<Bookmark>>> {
+    let bookmarks = state
+        .local_proxy
+        .bookmarks()
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(web::Json(bookmarks))
 }
 
 async fn endpoint_add(
     state: web::Data<State>,
     info: web::Json<AddParams>,
-) -> Result<web::Json<i32>> {
-    state
+) -> Result<web::Json<Bookmark>> {
+    let bookmark = state
         .local_proxy
-        .add(&info.url, &info.description, vec![])
-        .unwrap();
-    Ok(web::Json(0))
+        .add(&NewBookmark {
+            url: info.url.clone(),
+            description: info.description.clone(),
+            tags: info.tags.clone(),
+            metadata: info.metadata.clone(),
+        })
+        .map_err(error::ErrorBadRequest)?;
+    Ok(web::Json(bookmark))
 }
 
 async fn endpoint_delete(
     state: web::Data<State>,
     info: web::Json<DeleteParams>,
 ) -> Result<web::Json<i32>> {
-    state.local_proxy.delete(info.id).unwrap();
+    state
+        .local_proxy
+        .delete(info.id)
+        .map_err(error::ErrorBadRequest)?;
     Ok(web::Json(0))
 }
 
@@ -83,7 +118,7 @@ async fn endpoint_update_description(
     state
         .local_proxy
         .update_description(info.0 .0, &info.0 .1)
-        .unwrap();
+        .map_err(error::ErrorBadRequest)?;
     Ok(web::Json(0))
 }
 
@@ -91,7 +126,10 @@ async fn endpoint_update_url(
     state: web::Data<State>,
     info: web::Json<(i32, String)>,
 ) -> Result<web::Json<i32>> {
-    state.local_proxy.update_url(info.0 .0, &info.0 .1).unwrap();
+    state
+        .local_proxy
+        .update_url(info.0 .0, &info.0 .1)
+        .map_err(error::ErrorBadRequest)?;
     Ok(web::Json(0))
 }
 
@@ -102,18 +140,215 @@ async fn endpoint_update_tags(
     state
         .local_proxy
         .update_tags(info.0 .0, &info.0 .1)
-        .unwrap();
+        .map_err(error::ErrorBadRequest)?;
+    Ok(web::Json(0))
+}
+
+async fn endpoint_trash(state: web::Data<State>) -> Result<web::Json<Vec<Bookmark>>> {
+    let bookmarks = state
+        .local_proxy
+        .trash()
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(web::Json(bookmarks))
+}
+
+async fn endpoint_restore(
+    state: web::Data<State>,
+    info: web::Json<i32>,
+) -> Result<web::Json<Bookmark>> {
+    let bookmark = state
+        .local_proxy
+        .restore(info.0)
+        .map_err(error::ErrorBadRequest)?;
+    Ok(web::Json(bookmark))
+}
+
+async fn endpoint_empty_trash(state: web::Data<State>) -> Result<web::Json<usize>> {
+    let purged = state
+        .local_proxy
+        .empty_trash()
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(web::Json(purged))
+}
+
+async fn endpoint_history(
+    state: web::Data<State>,
+    info: web::Json<i32>,
+) -> Result<web::Json<Vec<Revision>>> {
+    let history = state
+        .local_proxy
+        .history(info.0)
+        .map_err(error::ErrorNotFound)?;
+    Ok(web::Json(history))
+}
+
+async fn endpoint_revert(
+    state: web::Data<State>,
+    info: web::Json<(i32, i32)>,
+) -> Result<web::Json<Bookmark>> {
+    let bookmark = state
+        .local_proxy
+        .revert(info.0 .0, info.0 .1)
+        .map_err(error::ErrorBadRequest)?;
+    Ok(web::Json(bookmark))
+}
+
+async fn endpoint_undo(state: web::Data<State>) -> Result<web::Json<Revision>> {
+    let revision = state.local_proxy.undo().map_err(error::ErrorBadRequest)?;
+    Ok(web::Json(revision))
+}
+
+async fn endpoint_record_check(
+    state: web::Data<State>,
+    info: web::Json<LinkCheck>,
+) -> Result<web::Json<i32>> {
+    state
+        .local_proxy
+        .record_check(&info)
+        .map_err(error::ErrorBadRequest)?;
+    Ok(web::Json(0))
+}
+
+async fn endpoint_link_checks(state: web::Data<State>) -> Result<web::Json<Vec<LinkCheck>>> {
+    let checks = state
+        .local_proxy
+        .link_checks()
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(web::Json(checks))
+}
+
+async fn endpoint_store_snapshot(
+    state: web::Data<State>,
+    info: web::Json<(i32, String)>,
+) -> Result<web::Json<Snapshot>> {
+    let snapshot = state
+        .local_proxy
+        .store_snapshot(info.0 .0, &info.0 .1)
+        .map_err(error::ErrorBadRequest)?;
+    Ok(web::Json(snapshot))
+}
+
+async fn endpoint_snapshots(
+    state: web::Data<State>,
+    info: web::Json<i32>,
+) -> Result<web::Json<Vec<Snapshot>>> {
+    let snapshots = state
+        .local_proxy
+        .snapshots(info.0)
+        .map_err(error::ErrorNotFound)?;
+    Ok(web::Json(snapshots))
+}
+
+async fn endpoint_snapshot(
+    state: web::Data<State>,
+    info: web::Json<String>,
+) -> Result<web::Json<String>> {
+    let content = state
+        .local_proxy
+        .snapshot(&info.0)
+        .map_err(error::ErrorNotFound)?;
+    Ok(web::Json(content))
+}
+
+async fn endpoint_set_page_text(
+    state: web::Data<State>,
+    info: web::Json<(i32, String)>,
+) -> Result<web::Json<i32>> {
+    state
+        .local_proxy
+        .set_page_text(info.0 .0, &info.0 .1)
+        .map_err(error::ErrorBadRequest)?;
     Ok(web::Json(0))
 }
 
+async fn endpoint_search(
+    state: web::Data<State>,
+    info: web::Json<String>,
+) -> Result<web::Json<Vec<SearchResult>>> {
+    let results = state
+        .local_proxy
+        .search(&info.0)
+        .map_err(error::ErrorBadRequest)?;
+    Ok(web::Json(results))
+}
+
+async fn endpoint_visit(state: web::Data<State>, info: web::Json<i32>) -> Result<web::Json<i32>> {
+    state
+        .local_proxy
+        .visit(info.0)
+        .map_err(error::ErrorBadRequest)?;
+    Ok(web::Json(0))
+}
+
+async fn endpoint_visits(state: web::Data<State>) -> Result<web::Json<Vec<Visits>>> {
+    let visits = state
+        .local_proxy
+        .visits()
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(web::Json(visits))
+}
+
+async fn endpoint_tags(state: web::Data<State>) -> Result<web::Json<Vec<TagCount>>> {
+    let tags = state
+        .local_proxy
+        .tags()
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(web::Json(tags))
+}
+
+/// Latest snapshot of a bookmark as a page for browsers. Archived pages are
+/// sandboxed so their scripts can't reach the rest of the api.
+async fn endpoint_archived(state: web::Data<State>, path: web::Path<i32>) -> Result<HttpResponse> {
+    let snapshot = state
+        .local_proxy
+        .snapshots(path.into_inner())
+        .map_err(error::ErrorNotFound)?
+        .into_iter()
+        .next()
+        .ok_or(error::ErrorNotFound("Bookmark has no snapshots"))?;
+    let content = state
+        .local_proxy
+        .snapshot(&snapshot.hash)
+        .map_err(error::ErrorNotFound)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .insert_header(("Content-Security-Policy", "sandbox"))
+        .body(content))
+}
+
+/// Check every bookmark url in the background, once every `interval`.
+fn schedule_checks(
+    db: String,
+    tracking_params: Vec<String>,
+    check_config: CheckConfig,
+    interval: Duration,
+) {
+    thread::spawn(move || loop {
+        thread::sleep(interval);
+
+        let local_proxy = LocalProxy::with_tracking_params(&db, tracking_params.clone());
+        match local_proxy
+            .bookmarks()
+            .and_then(|bookmarks| check(&local_proxy, &bookmarks, &check_config))
+        {
+            Ok(checks) => println!(
+                "Checked {} bookmarks, {} are broken",
+                checks.len(),
+                checks.iter().filter(|check| check.is_broken()).count()
+            ),
+            Err(err) => eprintln!("Failed to check bookmarks: {}", err),
+        }
+    });
+}
+
 pub fn server(cli: Cli) -> Result<(), String> {
     let config = Config::read().ok_or("Failed to read config".to_string())?;
 
-    let mut server_config = if let Some(server_config) = config.server {
-        server_config
-    } else {
-        ServerConfig::default()
-    };
+    let tracking_params = config.get_tracking_params();
+    let trash_retention_days = config.trash_retention_days;
+    let check_config = config.check.clone().unwrap_or_default();
+    let mut server_config = config.server.unwrap_or_default();
 
     server_config.set_db(&cli.db);
     server_config.set_host(&cli.host);
@@ -126,67 +361,121 @@ pub fn server(cli: Cli) -> Result<(), String> {
         server_config.get_port(),
         server_config.get_root()
     );
+
+    let db = server_config.db.clone().ok_or("Expected db path")?;
+    let listener = TcpListener::bind((server_config.get_host(), server_config.get_port()))
+        .map_err(|err| format!("{:?}", err))?;
+
+    if let Some(hours) = server_config.check_interval_hours {
+        schedule_checks(
+            db.clone(),
+            tracking_params.clone(),
+            check_config,
+            Duration::from_secs(hours.max(1) * 60 * 60),
+        );
+    }
+
     tokio::runtime::Builder::new_multi_thread()
         .enable_all()
         .build()
         .unwrap()
-        .block_on(async_server(
-            server_config.db.ok_or("Expected db path")?,
-            server_config.host.unwrap_or("127.0.0.1".to_string()),
-            server_config.port.unwrap_or(8080),
-            server_config.root.unwrap_or("/".to_string()),
-        ))
+        .block_on(serve(listener, server_config.get_root(), move || {
+            let mut local_proxy = LocalProxy::with_tracking_params(&db, tracking_params.clone());
+            local_proxy
+                .set_trash_retention(trash_retention_days)
+                .unwrap();
+            local_proxy
+        }))
         .map_err(|err| format!("{:?}", err))
 }
 
-async fn async_server(db: String, host: String, port: u16, root: String) -> std::io::Result<()> {
-    let (
-        bookmark_endpoint,
-        list_endpoint,
-        add_endpoint,
-        delete_endpoint,
-        update_description_endpoint,
-        update_url_endpoint,
-        update_tags_endpoint,
-    ) = if root == "/" {
-        (
-            "/bookmark".to_string(),
-            "/list".to_string(),
-            "/add".to_string(),
-            "/delete".to_string(),
-            "/update_description".to_string(),
-            "/update_url".to_string(),
-            "/update_tags".to_string(),
-        )
+fn endpoint(root: &str, name: &str) -> String {
+    if root == "/" {
+        format!("/{}", name)
     } else {
-        (
-            root.clone() + "/bookmark",
-            root.clone() + "/list",
-            root.clone() + "/add",
-            root.clone() + "/delete",
-            root.clone() + "/update_description",
-            root.clone() + "/update_url",
-            root.clone() + "/update_tags",
-        )
-    };
+        format!("{}/{}", root, name)
+    }
+}
+
+fn routes(cfg: &mut web::ServiceConfig, root: &str) {
+    cfg.route(
+        &endpoint(root, "bookmark"),
+        web::get().to(endpoint_bookmark),
+    )
+    .route(&endpoint(root, "list"), web::get().to(endpoint_list))
+    .route(&endpoint(root, "add"), web::post().to(endpoint_add))
+    .route(&endpoint(root, "delete"), web::post().to(endpoint_delete))
+    .route(
+        &endpoint(root, "update_description"),
+        web::post().to(endpoint_update_description),
+    )
+    .route(
+        &endpoint(root, "update_url"),
+        web::post().to(endpoint_update_url),
+    )
+    .route(
+        &endpoint(root, "update_tags"),
+        web::post().to(endpoint_update_tags),
+    )
+    .route(&endpoint(root, "trash"), web::get().to(endpoint_trash))
+    .route(&endpoint(root, "restore"), web::post().to(endpoint_restore))
+    .route(
+        &endpoint(root, "empty_trash"),
+        web::post().to(endpoint_empty_trash),
+    )
+    .route(&endpoint(root, "history"), web::get().to(endpoint_history))
+    .route(&endpoint(root, "revert"), web::post().to(endpoint_revert))
+    .route(&endpoint(root, "undo"), web::post().to(endpoint_undo))
+    .route(
+        &endpoint(root, "record_check"),
+        web::post().to(endpoint_record_check),
+    )
+    .route(
+        &endpoint(root, "link_checks"),
+        web::get().to(endpoint_link_checks),
+    )
+    .service(
+        web::resource(endpoint(root, "store_snapshot"))
+            .app_data(web::JsonConfig::default().limit(MAX_PAGE_PAYLOAD))
+            .route(web::post().to(endpoint_store_snapshot)),
+    )
+    .route(
+        &endpoint(root, "snapshots"),
+        web::get().to(endpoint_snapshots),
+    )
+    .route(
+        &endpoint(root, "snapshot"),
+        web::get().to(endpoint_snapshot),
+    )
+    .service(
+        web::resource(endpoint(root, "set_page_text"))
+            .app_data(web::JsonConfig::default().limit(MAX_PAGE_PAYLOAD))
+            .route(web::post().to(endpoint_set_page_text)),
+    )
+    .route(&endpoint(root, "search"), web::get().to(endpoint_search))
+    .route(&endpoint(root, "visit"), web::post().to(endpoint_visit))
+    .route(&endpoint(root, "visits"), web::get().to(endpoint_visits))
+    .route(&endpoint(root, "tags"), web::get().to(endpoint_tags))
+    .route(
+        &endpoint(root, "archive/{id}"),
+        web::get().to(endpoint_archived),
+    );
+}
 
+/// Serve the marcador API on an already bound listener. `local_proxy` opens
+/// the database for each worker.
+pub async fn serve<F>(listener: TcpListener, root: String, local_proxy: F) -> std::io::Result<()>
+where
+    F: Fn() -> LocalProxy + Clone + Send + 'static,
+{
     HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(State {
-                local_proxy: LocalProxy::new(&db),
+                local_proxy: local_proxy(),
             }))
-            .route(&bookmark_endpoint, web::get().to(endpoint_bookmark))
-            .route(&list_endpoint, web::get().to(endpoint_list))
-            .route(&add_endpoint, web::post().to(endpoint_add))
-            .route(&delete_endpoint, web::post().to(endpoint_delete))
-            .route(
-                &update_description_endpoint,
-                web::post().to(endpoint_update_description),
-            )
-            .route(&update_url_endpoint, web::post().to(endpoint_update_url))
-            .route(&update_tags_endpoint, web::post().to(endpoint_update_tags))
+            .configure(|cfg| routes(cfg, &root))
     })
-    .bind((host, port))?
+    .listen(listener)?
     .run()
     .await
 }
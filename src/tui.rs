@@ -0,0 +1,537 @@
+// Copyright 2024 João Freitas
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of
+// the GNU General Public License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS FOR
+// A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Terminal interface: a filterable bookmark list with a detail pane.
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+
+use std::cmp::Reverse;
+
+use crate::bookmark::{Bookmark, Visits};
+use crate::bookmark_proxy::BookmarkProxy;
+use crate::clipboard;
+use crate::config::Config;
+use crate::frecency::sort_by_frecency;
+use crate::fuzzy;
+use crate::opener::open_bookmark;
+
+/// Bookmark field edited in place
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Description,
+    Url,
+    Tags,
+}
+
+impl Field {
+    fn label(&self) -> &'static str {
+        match self {
+            Field::Description => "Description",
+            Field::Url => "Url",
+            Field::Tags => "Tags",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Mode {
+    Normal,
+    /// Typing the filter
+    Filter,
+    Edit(Field, String),
+}
+
+const HELP: &str =
+    "/ filter  ⏎ open  e description  l url  t tags  d delete  u undo  y copy url  r reload  q quit";
+
+pub struct App<'a> {
+    proxy: &'a dyn BookmarkProxy,
+    config: &'a Config,
+    bookmarks: Vec<Bookmark>,
+    visits: Vec<Visits>,
+    /// Indices in `bookmarks` of the bookmarks matching the filter
+    visible: Vec<usize>,
+    state: ListState,
+    filter: String,
+    mode: Mode,
+    status: Option<String>,
+    /// Ids of the deleted bookmarks, latest last
+    deleted: Vec<i32>,
+    quit: bool,
+}
+
+impl<'a> App<'a> {
+    pub fn new(proxy: &'a dyn BookmarkProxy, config: &'a Config) -> Result<App<'a>, String> {
+        let mut app = App {
+            proxy,
+            config,
+            bookmarks: vec![],
+            visits: vec![],
+            visible: vec![],
+            state: ListState::default(),
+            filter: String::new(),
+            mode: Mode::Normal,
+            status: None,
+            deleted: vec![],
+            quit: false,
+        };
+        app.reload()?;
+        Ok(app)
+    }
+
+    fn selected(&self) -> Option<&Bookmark> {
+        let index = *self.visible.get(self.state.selected()?)?;
+        self.bookmarks.get(index)
+    }
+
+    /// Fetch the bookmarks again, keeping the selected one if it still exists.
+    fn reload(&mut self) -> Result<(), String> {
+        let selected = self.selected().map(|bookmark| bookmark.bookmark.id);
+        self.bookmarks = self.proxy.bookmarks()?;
+        self.visits = self.proxy.visits()?;
+        sort_by_frecency(&mut self.bookmarks, &self.visits);
+        self.refilter(selected);
+        Ok(())
+    }
+
+    /// Apply the filter, best matches first, selecting bookmark `id` if visible
+    /// or the first one.
+    fn refilter(&mut self, id: Option<i32>) {
+        let mut scored: Vec<(i64, usize)> = self
+            .bookmarks
+            .iter()
+            .enumerate()
+            .filter_map(|(index, bookmark)| {
+                Some((fuzzy::bookmark_score(&self.filter, bookmark)?, index))
+            })
+            .collect();
+        scored.sort_by_key(|(score, _)| Reverse(*score));
+        self.visible = scored.into_iter().map(|(_, index)| index).collect();
+
+        let position = id.and_then(|id| {
+            self.visible
+                .iter()
+                .position(|index| self.bookmarks[*index].bookmark.id == id)
+        });
+        self.state.select(match position {
+            Some(position) => Some(position),
+            None if self.visible.is_empty() => None,
+            None => Some(
+                self.state
+                    .selected()
+                    .unwrap_or(0)
+                    .min(self.visible.len() - 1),
+            ),
+        });
+    }
+
+    fn move_by(&mut self, offset: isize) {
+        if self.visible.is_empty() {
+            return;
+        }
+        let current = self.state.selected().unwrap_or(0) as isize;
+        let last = self.visible.len() as isize - 1;
+        self.state
+            .select(Some((current + offset).clamp(0, last) as usize));
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        let result = match self.mode.clone() {
+            Mode::Normal => self.normal_key(key),
+            Mode::Filter => {
+                self.filter_key(key);
+                Ok(())
+            }
+            Mode::Edit(field, input) => self.edit_key(key, field, input),
+        };
+        if let Err(err) = result {
+            self.status = Some(err);
+        }
+    }
+
+    fn normal_key(&mut self, key: KeyEvent) -> Result<(), String> {
+        self.status = None;
+        match key.code {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => self.quit = true,
+            KeyCode::Esc if !self.filter.is_empty() => {
+                self.filter.clear();
+                let selected = self.selected().map(|bookmark| bookmark.bookmark.id);
+                self.refilter(selected);
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.move_by(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_by(-1),
+            KeyCode::PageDown => self.move_by(10),
+            KeyCode::PageUp => self.move_by(-10),
+            KeyCode::Home | KeyCode::Char('g') => self.move_by(isize::MIN / 2),
+            KeyCode::End | KeyCode::Char('G') => self.move_by(isize::MAX / 2),
+            KeyCode::Char('/') => self.mode = Mode::Filter,
+            KeyCode::Char('r') => self.reload()?,
+            KeyCode::Char('u') => self.undo()?,
+            _ => return self.bookmark_key(key),
+        }
+        Ok(())
+    }
+
+    /// Keys acting on the selected bookmark
+    fn bookmark_key(&mut self, key: KeyEvent) -> Result<(), String> {
+        let bookmark = match self.selected() {
+            Some(bookmark) => bookmark.clone(),
+            None => return Ok(()),
+        };
+
+        match key.code {
+            KeyCode::Enter | KeyCode::Char('o') => {
+                open_bookmark(&self.config.open.clone().unwrap_or_default(), &bookmark)?;
+                self.proxy.visit(bookmark.bookmark.id)?;
+                self.status = Some(format!("Opened {}", bookmark.bookmark.url));
+                self.reload()?;
+            }
+            KeyCode::Char('y') => {
+                clipboard::copy(bookmark.bookmark.url.clone())?;
+                self.status = Some(format!("Copied {}", bookmark.bookmark.url));
+            }
+            KeyCode::Char('e') => {
+                self.mode = Mode::Edit(Field::Description, bookmark.bookmark.description)
+            }
+            KeyCode::Char('l') => self.mode = Mode::Edit(Field::Url, bookmark.bookmark.url),
+            KeyCode::Char('t') => {
+                self.mode = Mode::Edit(Field::Tags, bookmark.tag_names().join(" "))
+            }
+            KeyCode::Char('d') | KeyCode::Delete => {
+                self.proxy.delete(bookmark.bookmark.id)?;
+                self.deleted.push(bookmark.bookmark.id);
+                self.status = Some(format!("Deleted {}, u to undo", bookmark.bookmark.url));
+                self.reload()?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Restore the latest deleted bookmark.
+    fn undo(&mut self) -> Result<(), String> {
+        let id = self.deleted.pop().ok_or("Nothing to undo")?;
+        let restored = self.proxy.restore(id)?;
+        self.status = Some(format!("Restored {}", restored.bookmark.url));
+        self.reload()?;
+        self.refilter(Some(id));
+        Ok(())
+    }
+
+    fn filter_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter => self.mode = Mode::Normal,
+            KeyCode::Esc => {
+                self.filter.clear();
+                self.mode = Mode::Normal;
+            }
+            KeyCode::Backspace => {
+                self.filter.pop();
+            }
+            KeyCode::Char(c) => self.filter.push(c),
+            _ => return,
+        }
+        self.refilter(None);
+    }
+
+    fn edit_key(&mut self, key: KeyEvent, field: Field, mut input: String) -> Result<(), String> {
+        match key.code {
+            KeyCode::Esc => {
+                self.mode = Mode::Normal;
+                return Ok(());
+            }
+            KeyCode::Enter => {
+                self.mode = Mode::Normal;
+                return self.save(field, &input);
+            }
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => input.clear(),
+            KeyCode::Char(c) => input.push(c),
+            _ => {}
+        }
+        self.mode = Mode::Edit(field, input);
+        Ok(())
+    }
+
+    fn save(&mut self, field: Field, input: &str) -> Result<(), String> {
+        let id = match self.selected() {
+            Some(bookmark) => bookmark.bookmark.id,
+            None => return Ok(()),
+        };
+
+        match field {
+            Field::Description => self.proxy.update_description(id, input)?,
+            Field::Url => self.proxy.update_url(id, input)?,
+            Field::Tags => {
+                let tags: Vec<String> = input.split_whitespace().map(str::to_string).collect();
+                self.proxy.update_tags(id, &tags)?
+            }
+        };
+        self.status = Some(format!("Saved {}", field.label().to_lowercase()));
+        self.reload()
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [top, main, bottom] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [list_area, detail_area] =
+            Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .areas(main);
+
+        let filter_style = if self.mode == Mode::Filter {
+            Style::new().fg(Color::Yellow)
+        } else {
+            Style::new()
+        };
+        frame.render_widget(
+            Line::from(vec![
+                Span::styled("/ ", filter_style),
+                Span::styled(self.filter.as_str(), filter_style),
+                Span::raw(format!("  {}/{}", self.visible.len(), self.bookmarks.len())).dim(),
+            ]),
+            top,
+        );
+
+        let items: Vec<ListItem> = self
+            .visible
+            .iter()
+            .map(|index| list_item(&self.bookmarks[*index]))
+            .collect();
+        let list = List::new(items)
+            .block(Block::new().borders(Borders::RIGHT))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, list_area, &mut self.state);
+
+        let detail = self
+            .selected()
+            .map(|bookmark| details(bookmark, &self.visits))
+            .unwrap_or_default();
+        frame.render_widget(
+            Paragraph::new(detail)
+                .wrap(Wrap { trim: false })
+                .block(Block::new().borders(Borders::NONE)),
+            detail_area,
+        );
+
+        let footer = match (&self.mode, &self.status) {
+            (Mode::Edit(field, input), _) => Line::from(vec![
+                Span::styled(
+                    format!("{}: ", field.label()),
+                    Style::new().fg(Color::Yellow),
+                ),
+                Span::raw(input.as_str()),
+            ]),
+            (_, Some(status)) => Line::from(status.as_str()),
+            (Mode::Filter, None) => Line::from("⏎ keep filter  esc clear filter").dim(),
+            (Mode::Normal, None) => Line::from(HELP).dim(),
+        };
+        frame.render_widget(footer, bottom);
+    }
+}
+
+fn list_item(bookmark: &Bookmark) -> ListItem<'static> {
+    let description = if bookmark.bookmark.description.is_empty() {
+        bookmark.bookmark.url.clone()
+    } else {
+        bookmark.bookmark.description.clone()
+    };
+    let tags = bookmark
+        .tags
+        .iter()
+        .map(|tag| format!("#{}", tag.tag))
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    ListItem::new(Line::from(vec![
+        Span::raw(description).bold(),
+        Span::raw("  "),
+        Span::styled(tags, Style::new().fg(Color::Blue)),
+    ]))
+}
+
+fn details(bookmark: &Bookmark, visits: &[Visits]) -> Vec<Line<'static>> {
+    let field = |name: &str, value: String| {
+        Line::from(vec![
+            Span::styled(format!("{:<12}", name), Style::new().fg(Color::Yellow)),
+            Span::raw(value),
+        ])
+    };
+    let mut lines = vec![
+        field("Id", bookmark.bookmark.id.to_string()),
+        field("Description", bookmark.bookmark.description.clone()),
+        field("Url", bookmark.bookmark.url.clone()),
+        field("Tags", bookmark.tag_names().join(" ")),
+    ];
+
+    if let Some(metadata) = bookmark.metadata() {
+        if let Some(title) = metadata.best_title() {
+            lines.push(field("Title", title));
+        }
+        if let Some(description) = metadata.description {
+            lines.push(field("About", description));
+        }
+    }
+    if let Some(visits) = visits
+        .iter()
+        .find(|visits| visits.bookmark_id == bookmark.bookmark.id)
+    {
+        lines.push(field("Visits", visits.count.to_string()));
+    }
+
+    lines
+}
+
+fn run(terminal: &mut DefaultTerminal, app: &mut App) -> Result<(), String> {
+    while !app.quit {
+        terminal
+            .draw(|frame| app.draw(frame))
+            .map_err(|err| format!("{}", err))?;
+        if let Event::Key(key) = event::read().map_err(|err| format!("{}", err))? {
+            if key.kind == KeyEventKind::Press {
+                app.handle_key(key);
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn command_tui(proxy: &dyn BookmarkProxy, config: &Config) -> Result<(), String> {
+    let mut app = App::new(proxy, config)?;
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &mut app);
+    ratatui::restore();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bookmark::NewBookmark;
+    use crate::local_proxy::LocalProxy;
+
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    fn press(app: &mut App, keys: &str) {
+        for c in keys.chars() {
+            let code = match c {
+                '\n' => KeyCode::Enter,
+                '\x1b' => KeyCode::Esc,
+                '\x08' => KeyCode::Backspace,
+                c => KeyCode::Char(c),
+            };
+            app.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+        }
+    }
+
+    fn ids(app: &App) -> Vec<i32> {
+        app.visible
+            .iter()
+            .map(|index| app.bookmarks[*index].bookmark.id)
+            .collect()
+    }
+
+    #[test]
+    fn bookmarks_are_filtered_edited_and_deleted() {
+        let dir = tempfile::tempdir().unwrap();
+        let proxy = LocalProxy::new(dir.path().join("marcador.db").to_str().unwrap());
+        for (url, description, tags) in [
+            ("https://www.rust-lang.org", "Rust", vec!["lang"]),
+            ("https://docs.rs", "Docs", vec!["rust", "docs"]),
+            ("https://python.org", "Python", vec!["lang"]),
+        ] {
+            proxy
+                .add(&NewBookmark {
+                    url: url.to_string(),
+                    description: description.to_string(),
+                    tags: tags.into_iter().map(str::to_string).collect(),
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+        let config = Config::default();
+        let mut app = App::new(&proxy, &config).unwrap();
+        assert_eq!(ids(&app), vec![1, 2, 3]);
+
+        press(&mut app, "/rust\n");
+        assert_eq!(ids(&app), vec![1, 2]);
+        press(&mut app, "j");
+        assert_eq!(app.selected().unwrap().bookmark.id, 2);
+
+        press(&mut app, "t\x08\x08\x08\x08api\n");
+        assert_eq!(proxy.bookmark(2).unwrap().tag_names(), vec!["rust", "api"]);
+        press(&mut app, "eDocs.rs\x1b");
+        assert_eq!(proxy.bookmark(2).unwrap().bookmark.description, "Docs");
+
+        press(&mut app, "d");
+        assert_eq!(ids(&app), vec![1]);
+        assert!(proxy.bookmark(2).is_err());
+        press(&mut app, "u");
+        assert_eq!(ids(&app), vec![1, 2]);
+        assert_eq!(app.selected().unwrap().bookmark.id, 2);
+        press(&mut app, "u");
+        assert_eq!(app.status.as_deref(), Some("Nothing to undo"));
+
+        press(&mut app, "\x1b");
+        assert_eq!(ids(&app), vec![1, 2, 3]);
+        press(&mut app, "/pthn\n");
+        assert_eq!(ids(&app), vec![3]);
+        press(&mut app, "/\x08\x08\x08\x08rs\n");
+        assert_eq!(ids(&app), vec![2, 1]);
+        press(&mut app, "q");
+        assert!(app.quit);
+    }
+
+    #[test]
+    fn selection_is_shown_in_detail() {
+        let dir = tempfile::tempdir().unwrap();
+        let proxy = LocalProxy::new(dir.path().join("marcador.db").to_str().unwrap());
+        proxy
+            .add(&NewBookmark {
+                url: "https://www.rust-lang.org".to_string(),
+                description: "Rust".to_string(),
+                tags: vec!["lang".to_string()],
+                ..Default::default()
+            })
+            .unwrap();
+        let config = Config::default();
+        let mut app = App::new(&proxy, &config).unwrap();
+
+        let mut terminal = Terminal::new(TestBackend::new(100, 6)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+
+        assert!(screen.contains("Rust  #lang"));
+        assert!(screen.contains("Url         https://www.rust-lang.org"));
+        assert!(screen.contains("1/1"));
+    }
+}
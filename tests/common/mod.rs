@@ -0,0 +1,53 @@
+// Copyright 2024 João Freitas
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of
+// the GNU General Public License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS FOR
+// A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+// Every integration test crate compiles this module but only uses part of it.
+#![allow(dead_code)]
+
+use std::net::TcpListener;
+use std::thread;
+
+use tempfile::TempDir;
+
+use marcador::local_proxy::LocalProxy;
+use marcador::remote_proxy::RemoteProxy;
+use marcador::server::serve;
+
+/// Path to a fresh database inside a temporary directory, removed when the
+/// directory is dropped.
+pub fn temp_db() -> (TempDir, String) {
+    let dir = tempfile::tempdir().unwrap();
+    let db = dir.path().join("marcador.db").to_str().unwrap().to_string();
+    (dir, db)
+}
+
+pub fn local_proxy() -> (TempDir, LocalProxy) {
+    let (dir, db) = temp_db();
+    let proxy = LocalProxy::new(&db);
+    (dir, proxy)
+}
+
+/// Start a marcador server on an ephemeral port and return a proxy talking to it.
+pub fn remote_proxy() -> (TempDir, RemoteProxy) {
+    let (dir, db) = temp_db();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        actix_web::rt::System::new().block_on(serve(listener, "/".to_string(), move || {
+            LocalProxy::new(&db)
+        }))
+    });
+
+    (dir, RemoteProxy::new(&format!("http://{}", addr)))
+}
@@ -0,0 +1,696 @@
+// Copyright 2024 João Freitas
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of
+// the GNU General Public License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS FOR
+// A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Contract every `BookmarkProxy` implementation must honour. Each check runs
+//! against a fresh, empty backend.
+
+mod common;
+
+use tempfile::TempDir;
+
+use std::panic::{self, AssertUnwindSafe};
+
+use marcador::bookmark::{Bookmark, LinkCheck, NewBookmark};
+use marcador::bookmark_proxy::BookmarkProxy;
+use marcador::frecency::sort_by_frecency;
+use marcador::metadata::PageMetadata;
+
+type Backend = fn() -> (TempDir, Box<dyn BookmarkProxy>);
+type Check = fn(&dyn BookmarkProxy);
+
+const CHECKS: &[(&str, Check)] = &[
+    (
+        "add returns the stored bookmark",
+        add_returns_stored_bookmark,
+    ),
+    ("metadata is stored", metadata_is_stored),
+    ("duplicate urls are rejected", duplicate_urls_are_rejected),
+    (
+        "near-duplicate urls are rejected",
+        near_duplicate_urls_are_rejected,
+    ),
+    ("missing bookmarks are errors", missing_bookmarks_are_errors),
+    ("delete removes the bookmark", delete_removes_the_bookmark),
+    ("updates change single fields", updates_change_single_fields),
+    (
+        "update_tags replaces the tag set",
+        update_tags_replaces_the_tag_set,
+    ),
+    ("tags are per bookmark", tags_are_per_bookmark),
+    (
+        "deleted bookmarks go to the trash",
+        deleted_bookmarks_go_to_the_trash,
+    ),
+    (
+        "restore brings bookmarks back",
+        restore_brings_bookmarks_back,
+    ),
+    (
+        "empty_trash purges deleted bookmarks",
+        empty_trash_purges_deleted_bookmarks,
+    ),
+    ("history records every change", history_records_every_change),
+    (
+        "revert restores an earlier revision",
+        revert_restores_an_earlier_revision,
+    ),
+    (
+        "undo reverses the latest change",
+        undo_reverses_the_latest_change,
+    ),
+    ("link checks are recorded", link_checks_are_recorded),
+    ("snapshots are archived", snapshots_are_archived),
+    ("large snapshots are archived", large_snapshots_are_archived),
+    ("search finds page text", search_finds_page_text),
+    ("visits are counted", visits_are_counted),
+    ("tags are counted", tags_are_counted),
+];
+
+fn conformance(backend: Backend) {
+    for (name, check) in CHECKS {
+        let (_dir, proxy) = backend();
+        if panic::catch_unwind(AssertUnwindSafe(|| check(&*proxy))).is_err() {
+            panic!("check failed: {}", name);
+        }
+    }
+}
+
+#[test]
+fn local_proxy_conformance() {
+    conformance(|| {
+        let (dir, proxy) = common::local_proxy();
+        (dir, Box::new(proxy))
+    });
+}
+
+#[test]
+fn remote_proxy_conformance() {
+    conformance(|| {
+        let (dir, proxy) = common::remote_proxy();
+        (dir, Box::new(proxy))
+    });
+}
+
+fn new_bookmark(url: &str, description: &str, tags: &[&str]) -> NewBookmark {
+    NewBookmark {
+        url: url.to_string(),
+        description: description.to_string(),
+        tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        ..Default::default()
+    }
+}
+
+fn tags(bookmark: &Bookmark) -> Vec<String> {
+    let mut tags: Vec<String> = bookmark.tags.iter().map(|tag| tag.tag.clone()).collect();
+    tags.sort();
+    tags
+}
+
+fn ids(bookmarks: &[Bookmark]) -> Vec<i32> {
+    bookmarks
+        .iter()
+        .map(|bookmark| bookmark.bookmark.id)
+        .collect()
+}
+
+fn add_returns_stored_bookmark(proxy: &dyn BookmarkProxy) {
+    let added = proxy
+        .add(&new_bookmark("https://example.com", "Example", &["b", "a"]))
+        .unwrap();
+
+    assert_eq!(added.bookmark.url, "https://example.com");
+    assert_eq!(added.bookmark.description, "Example");
+    assert_eq!(tags(&added), vec!["a", "b"]);
+
+    let stored = proxy.bookmark(added.bookmark.id).unwrap();
+    assert_eq!(stored.bookmark.url, added.bookmark.url);
+    assert_eq!(stored.bookmark.description, added.bookmark.description);
+    assert_eq!(tags(&stored), tags(&added));
+
+    assert_eq!(ids(&proxy.bookmarks().unwrap()), vec![added.bookmark.id]);
+}
+
+fn metadata_is_stored(proxy: &dyn BookmarkProxy) {
+    let metadata = PageMetadata {
+        title: Some("Example Domain".to_string()),
+        favicon: Some("https://example.com/favicon.ico".to_string()),
+        ..Default::default()
+    };
+    let added = proxy
+        .add(&NewBookmark {
+            metadata: Some(metadata.clone()),
+            ..new_bookmark("https://example.com", "Example", &[])
+        })
+        .unwrap();
+    let plain = proxy
+        .add(&new_bookmark("https://example.org", "Plain", &[]))
+        .unwrap();
+
+    assert_eq!(added.metadata(), Some(metadata.clone()));
+    assert_eq!(
+        proxy.bookmark(added.bookmark.id).unwrap().metadata(),
+        Some(metadata)
+    );
+    assert_eq!(proxy.bookmark(plain.bookmark.id).unwrap().metadata(), None);
+}
+
+fn duplicate_urls_are_rejected(proxy: &dyn BookmarkProxy) {
+    proxy
+        .add(&new_bookmark("https://example.com", "Example", &[]))
+        .unwrap();
+
+    assert!(proxy
+        .add(&new_bookmark("https://example.com", "Again", &["x"]))
+        .is_err());
+    assert_eq!(proxy.bookmarks().unwrap().len(), 1);
+}
+
+fn near_duplicate_urls_are_rejected(proxy: &dyn BookmarkProxy) {
+    proxy
+        .add(&new_bookmark("https://x.com/a", "A", &[]))
+        .unwrap();
+    let other = proxy
+        .add(&new_bookmark("https://x.com/b", "B", &[]))
+        .unwrap();
+
+    for url in [
+        "http://x.com/a",
+        "https://X.com/a/",
+        "https://x.com/a?utm_source=foo",
+    ] {
+        assert!(
+            proxy.add(&new_bookmark(url, "Again", &[])).is_err(),
+            "{}",
+            url
+        );
+    }
+    assert!(proxy
+        .update_url(other.bookmark.id, "http://x.com/a/")
+        .is_err());
+    proxy
+        .update_url(other.bookmark.id, "https://x.com/b/")
+        .unwrap();
+    assert_eq!(proxy.bookmarks().unwrap().len(), 2);
+}
+
+fn missing_bookmarks_are_errors(proxy: &dyn BookmarkProxy) {
+    assert!(proxy.bookmark(42).is_err());
+    assert!(proxy.delete(42).is_err());
+    assert!(proxy.update_description(42, "Nothing").is_err());
+    assert!(proxy.update_url(42, "https://example.com").is_err());
+    assert!(proxy.update_tags(42, &["x".to_string()]).is_err());
+    assert!(proxy.bookmarks().unwrap().is_empty());
+}
+
+fn delete_removes_the_bookmark(proxy: &dyn BookmarkProxy) {
+    let first = proxy
+        .add(&new_bookmark("https://one.com", "One", &["x"]))
+        .unwrap();
+    let second = proxy
+        .add(&new_bookmark("https://two.com", "Two", &["x"]))
+        .unwrap();
+
+    proxy.delete(first.bookmark.id).unwrap();
+
+    assert!(proxy.bookmark(first.bookmark.id).is_err());
+    assert!(proxy.delete(first.bookmark.id).is_err());
+    assert_eq!(ids(&proxy.bookmarks().unwrap()), vec![second.bookmark.id]);
+    assert_eq!(
+        tags(&proxy.bookmark(second.bookmark.id).unwrap()),
+        vec!["x"]
+    );
+
+    let again = proxy
+        .add(&new_bookmark("https://one.com", "One", &[]))
+        .unwrap();
+    assert!(tags(&again).is_empty());
+}
+
+fn updates_change_single_fields(proxy: &dyn BookmarkProxy) {
+    let id = proxy
+        .add(&new_bookmark("https://example.com", "Example", &["x"]))
+        .unwrap()
+        .bookmark
+        .id;
+
+    proxy.update_description(id, "Changed").unwrap();
+    let bookmark = proxy.bookmark(id).unwrap();
+    assert_eq!(bookmark.bookmark.description, "Changed");
+    assert_eq!(bookmark.bookmark.url, "https://example.com");
+
+    proxy.update_url(id, "https://example.org").unwrap();
+    let bookmark = proxy.bookmark(id).unwrap();
+    assert_eq!(bookmark.bookmark.description, "Changed");
+    assert_eq!(bookmark.bookmark.url, "https://example.org");
+    assert_eq!(tags(&bookmark), vec!["x"]);
+}
+
+fn update_tags_replaces_the_tag_set(proxy: &dyn BookmarkProxy) {
+    let id = proxy
+        .add(&new_bookmark("https://example.com", "Example", &["a", "b"]))
+        .unwrap()
+        .bookmark
+        .id;
+
+    proxy
+        .update_tags(id, &["b".to_string(), "c".to_string()])
+        .unwrap();
+    assert_eq!(tags(&proxy.bookmark(id).unwrap()), vec!["b", "c"]);
+
+    proxy
+        .update_tags(id, &["c".to_string(), "c".to_string()])
+        .unwrap();
+    assert_eq!(tags(&proxy.bookmark(id).unwrap()), vec!["c"]);
+
+    proxy.update_tags(id, &[]).unwrap();
+    assert!(tags(&proxy.bookmark(id).unwrap()).is_empty());
+}
+
+fn tags_are_per_bookmark(proxy: &dyn BookmarkProxy) {
+    let first = proxy
+        .add(&new_bookmark("https://one.com", "One", &["shared"]))
+        .unwrap()
+        .bookmark
+        .id;
+    let second = proxy
+        .add(&new_bookmark("https://two.com", "Two", &["shared", "own"]))
+        .unwrap()
+        .bookmark
+        .id;
+
+    proxy.update_tags(first, &["other".to_string()]).unwrap();
+
+    assert_eq!(tags(&proxy.bookmark(first).unwrap()), vec!["other"]);
+    assert_eq!(
+        tags(&proxy.bookmark(second).unwrap()),
+        vec!["own", "shared"]
+    );
+}
+
+fn deleted_bookmarks_go_to_the_trash(proxy: &dyn BookmarkProxy) {
+    let id = proxy
+        .add(&new_bookmark("https://example.com", "Example", &["x"]))
+        .unwrap()
+        .bookmark
+        .id;
+    assert!(proxy.trash().unwrap().is_empty());
+
+    proxy.delete(id).unwrap();
+
+    let trash = proxy.trash().unwrap();
+    assert_eq!(ids(&trash), vec![id]);
+    assert!(trash[0].bookmark.deleted_at.is_some());
+    assert_eq!(tags(&trash[0]), vec!["x"]);
+    assert!(proxy.update_description(id, "Changed").is_err());
+}
+
+fn restore_brings_bookmarks_back(proxy: &dyn BookmarkProxy) {
+    let id = proxy
+        .add(&new_bookmark("https://example.com", "Example", &["x"]))
+        .unwrap()
+        .bookmark
+        .id;
+    assert!(proxy.restore(id).is_err());
+
+    proxy.delete(id).unwrap();
+    let restored = proxy.restore(id).unwrap();
+
+    assert_eq!(restored.bookmark.id, id);
+    assert!(restored.bookmark.deleted_at.is_none());
+    assert_eq!(tags(&restored), vec!["x"]);
+    assert!(proxy.trash().unwrap().is_empty());
+    assert_eq!(ids(&proxy.bookmarks().unwrap()), vec![id]);
+
+    proxy.delete(id).unwrap();
+    proxy
+        .add(&new_bookmark("https://example.com", "Again", &[]))
+        .unwrap();
+    assert!(proxy.restore(id).is_err());
+}
+
+fn empty_trash_purges_deleted_bookmarks(proxy: &dyn BookmarkProxy) {
+    let first = proxy
+        .add(&new_bookmark("https://one.com", "One", &[]))
+        .unwrap()
+        .bookmark
+        .id;
+    let second = proxy
+        .add(&new_bookmark("https://two.com", "Two", &[]))
+        .unwrap()
+        .bookmark
+        .id;
+
+    proxy.delete(first).unwrap();
+    assert_eq!(proxy.empty_trash().unwrap(), 1);
+
+    assert!(proxy.trash().unwrap().is_empty());
+    assert!(proxy.restore(first).is_err());
+    assert_eq!(ids(&proxy.bookmarks().unwrap()), vec![second]);
+    assert_eq!(proxy.empty_trash().unwrap(), 0);
+}
+
+fn actions(proxy: &dyn BookmarkProxy, id: i32) -> Vec<String> {
+    proxy
+        .history(id)
+        .unwrap()
+        .into_iter()
+        .map(|revision| revision.action)
+        .collect()
+}
+
+fn history_records_every_change(proxy: &dyn BookmarkProxy) {
+    let id = proxy
+        .add(&new_bookmark("https://example.com", "Example", &["x"]))
+        .unwrap()
+        .bookmark
+        .id;
+
+    proxy.update_description(id, "Changed").unwrap();
+    proxy.update_description(id, "Changed").unwrap();
+    proxy.update_tags(id, &["y".to_string()]).unwrap();
+    proxy.delete(id).unwrap();
+    proxy.restore(id).unwrap();
+
+    let history = proxy.history(id).unwrap();
+    assert_eq!(
+        actions(proxy, id),
+        vec!["add", "update", "update", "delete", "restore"]
+    );
+    assert_eq!(
+        history
+            .iter()
+            .map(|revision| revision.rev)
+            .collect::<Vec<i32>>(),
+        vec![1, 2, 3, 4, 5]
+    );
+    assert_eq!(history[0].description, "Example");
+    assert_eq!(history[0].tags, vec!["x"]);
+    assert_eq!(history[1].description, "Changed");
+    assert_eq!(history[2].tags, vec!["y"]);
+    assert!(proxy.history(42).is_err());
+}
+
+fn revert_restores_an_earlier_revision(proxy: &dyn BookmarkProxy) {
+    let id = proxy
+        .add(&new_bookmark("https://example.com", "Example", &["x"]))
+        .unwrap()
+        .bookmark
+        .id;
+    proxy.update_url(id, "https://example.org").unwrap();
+    proxy.update_description(id, "").unwrap();
+    proxy.update_tags(id, &[]).unwrap();
+
+    let reverted = proxy.revert(id, 1).unwrap();
+
+    assert_eq!(reverted.bookmark.url, "https://example.com");
+    assert_eq!(reverted.bookmark.description, "Example");
+    assert_eq!(tags(&reverted), vec!["x"]);
+    assert_eq!(actions(proxy, id).last().unwrap(), "revert");
+    assert!(proxy.revert(id, 42).is_err());
+}
+
+fn undo_reverses_the_latest_change(proxy: &dyn BookmarkProxy) {
+    assert!(proxy.undo().is_err());
+
+    let first = proxy
+        .add(&new_bookmark("https://one.com", "One", &["x"]))
+        .unwrap()
+        .bookmark
+        .id;
+    let second = proxy
+        .add(&new_bookmark("https://two.com", "Two", &[]))
+        .unwrap()
+        .bookmark
+        .id;
+    proxy.update_url(first, "").unwrap();
+
+    let undone = proxy.undo().unwrap();
+    assert_eq!(undone.bookmark_id, first);
+    assert_eq!(undone.action, "update");
+    assert_eq!(
+        proxy.bookmark(first).unwrap().bookmark.url,
+        "https://one.com"
+    );
+    assert_eq!(actions(proxy, first), vec!["add"]);
+
+    proxy.delete(first).unwrap();
+    assert_eq!(proxy.undo().unwrap().action, "delete");
+    assert!(proxy.bookmark(first).is_ok());
+    assert!(proxy.trash().unwrap().is_empty());
+
+    assert_eq!(proxy.undo().unwrap().bookmark_id, second);
+    assert!(proxy.bookmark(second).is_err());
+    assert_eq!(ids(&proxy.trash().unwrap()), vec![second]);
+    assert_eq!(ids(&proxy.bookmarks().unwrap()), vec![first]);
+    assert_eq!(proxy.undo().unwrap().bookmark_id, first);
+    assert!(proxy.undo().is_err());
+}
+
+fn link_check(bookmark: &Bookmark, status: Option<i32>) -> LinkCheck {
+    LinkCheck {
+        bookmark_id: bookmark.bookmark.id,
+        url: bookmark.bookmark.url.clone(),
+        status,
+        redirect: None,
+        error: None,
+        checked_at: 1,
+    }
+}
+
+fn link_checks_are_recorded(proxy: &dyn BookmarkProxy) {
+    let a = proxy.add(&new_bookmark("https://a.com", "A", &[])).unwrap();
+    let b = proxy.add(&new_bookmark("https://b.com", "B", &[])).unwrap();
+    let c = proxy.add(&new_bookmark("https://c.com", "C", &[])).unwrap();
+    assert!(proxy.link_checks().unwrap().is_empty());
+
+    proxy.record_check(&link_check(&a, Some(200))).unwrap();
+    proxy.record_check(&link_check(&b, Some(404))).unwrap();
+    proxy.record_check(&link_check(&c, None)).unwrap();
+    proxy.record_check(&link_check(&a, Some(500))).unwrap();
+    assert_eq!(
+        proxy.link_checks().unwrap(),
+        vec![
+            link_check(&a, Some(500)),
+            link_check(&b, Some(404)),
+            link_check(&c, None),
+        ]
+    );
+
+    proxy.delete(b.bookmark.id).unwrap();
+    proxy.update_url(c.bookmark.id, "https://c.org").unwrap();
+    assert_eq!(
+        proxy.link_checks().unwrap(),
+        vec![link_check(&a, Some(500))]
+    );
+
+    let mut missing = link_check(&a, Some(200));
+    missing.bookmark_id = 1000;
+    assert!(proxy.record_check(&missing).is_err());
+}
+
+fn snapshots_are_archived(proxy: &dyn BookmarkProxy) {
+    let a = proxy.add(&new_bookmark("https://a.com", "A", &[])).unwrap();
+    let b = proxy.add(&new_bookmark("https://b.com", "B", &[])).unwrap();
+    assert!(proxy.snapshots(a.bookmark.id).unwrap().is_empty());
+
+    let first = proxy.store_snapshot(a.bookmark.id, "<p>first</p>").unwrap();
+    let second = proxy
+        .store_snapshot(a.bookmark.id, "<p>second</p>")
+        .unwrap();
+    let shared = proxy.store_snapshot(b.bookmark.id, "<p>first</p>").unwrap();
+    assert_eq!(first.url, "https://a.com");
+    assert_eq!(first.size, 12);
+    assert_eq!(first.hash, shared.hash);
+    assert_ne!(first.hash, second.hash);
+
+    let hashes: Vec<String> = proxy
+        .snapshots(a.bookmark.id)
+        .unwrap()
+        .into_iter()
+        .map(|snapshot| snapshot.hash)
+        .collect();
+    assert_eq!(hashes.len(), 2);
+    assert!(hashes.contains(&first.hash) && hashes.contains(&second.hash));
+    assert_eq!(proxy.snapshot(&second.hash).unwrap(), "<p>second</p>");
+    assert!(proxy.snapshot(&"0".repeat(40)).is_err());
+    assert!(proxy.snapshot("../secret").is_err());
+    assert!(proxy.store_snapshot(1000, "<p>missing</p>").is_err());
+
+    proxy.delete(a.bookmark.id).unwrap();
+    proxy.empty_trash().unwrap();
+    assert!(proxy.snapshot(&second.hash).is_err());
+    assert_eq!(proxy.snapshot(&first.hash).unwrap(), "<p>first</p>");
+}
+
+fn search_results(proxy: &dyn BookmarkProxy, query: &str) -> Vec<(i32, Option<String>)> {
+    proxy
+        .search(query)
+        .unwrap()
+        .into_iter()
+        .map(|result| (result.bookmark.bookmark.id, result.snippet))
+        .collect()
+}
+
+fn large_snapshots_are_archived(proxy: &dyn BookmarkProxy) {
+    let a = proxy.add(&new_bookmark("https://a.com", "A", &[])).unwrap();
+
+    // Larger than the default 2 MiB json limit of the server
+    let content = format!("<p>{}</p>", "x".repeat(3 * 1024 * 1024));
+    let snapshot = proxy.store_snapshot(a.bookmark.id, &content).unwrap();
+    assert_eq!(snapshot.size as usize, content.len());
+    assert_eq!(proxy.snapshot(&snapshot.hash).unwrap(), content);
+
+    proxy.set_page_text(a.bookmark.id, &content).unwrap();
+}
+
+fn search_finds_page_text(proxy: &dyn BookmarkProxy) {
+    let rust = proxy
+        .add(&new_bookmark(
+            "https://rust-lang.org",
+            "Rust",
+            &["programming"],
+        ))
+        .unwrap();
+    let book = proxy
+        .add(&new_bookmark(
+            "https://doc.rust-lang.org/book",
+            "The book",
+            &[],
+        ))
+        .unwrap();
+    let (rust, book) = (rust.bookmark.id, book.bookmark.id);
+
+    proxy
+        .set_page_text(book, "Each value in Rust has an owner")
+        .unwrap();
+    proxy
+        .store_snapshot(rust, "<p>A language empowering everyone</p>")
+        .unwrap();
+
+    assert_eq!(
+        search_results(proxy, "owner VALUE"),
+        vec![(
+            book,
+            Some("Each <mark>value</mark> in Rust has an <mark>owner</mark>".to_string())
+        )]
+    );
+    assert_eq!(
+        search_results(proxy, "empowering"),
+        vec![(
+            rust,
+            Some("A language <mark>empowering</mark> everyone".to_string())
+        )]
+    );
+    assert_eq!(
+        search_results(proxy, "rust"),
+        vec![
+            (rust, None),
+            (
+                book,
+                Some("Each value in <mark>Rust</mark> has an owner".to_string())
+            )
+        ]
+    );
+    assert_eq!(search_results(proxy, "programming"), vec![(rust, None)]);
+
+    let stack = proxy
+        .add(&new_bookmark("https://example.com/stack", "Stack", &[]))
+        .unwrap()
+        .bookmark
+        .id;
+    let ranked = |query: &str| -> Vec<i32> {
+        search_results(proxy, query)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect()
+    };
+    assert_eq!(ranked("rust lang"), vec![rust, book]);
+    assert_eq!(ranked("st"), vec![stack, rust, book]);
+    proxy.delete(stack).unwrap();
+    assert!(search_results(proxy, "\"missing").is_empty());
+    assert!(proxy.search("  ").is_err());
+
+    proxy.delete(book).unwrap();
+    assert!(search_results(proxy, "owner").is_empty());
+    assert!(proxy.set_page_text(book, "text").is_err());
+}
+
+fn visits_are_counted(proxy: &dyn BookmarkProxy) {
+    let a = proxy.add(&new_bookmark("https://a.com", "A", &[])).unwrap();
+    let b = proxy.add(&new_bookmark("https://b.com", "B", &[])).unwrap();
+    let c = proxy.add(&new_bookmark("https://c.com", "C", &[])).unwrap();
+    assert!(proxy.visits().unwrap().is_empty());
+
+    proxy.visit(b.bookmark.id).unwrap();
+    proxy.visit(b.bookmark.id).unwrap();
+    proxy.visit(c.bookmark.id).unwrap();
+    assert!(proxy.visit(1000).is_err());
+
+    let visits = proxy.visits().unwrap();
+    let counts: Vec<(i32, i64)> = visits
+        .iter()
+        .map(|visits| (visits.bookmark_id, visits.count))
+        .collect();
+    assert_eq!(counts, vec![(b.bookmark.id, 2), (c.bookmark.id, 1)]);
+    assert!(visits[0].frecency > visits[1].frecency);
+
+    let mut bookmarks = proxy.bookmarks().unwrap();
+    sort_by_frecency(&mut bookmarks, &visits);
+    assert_eq!(
+        ids(&bookmarks),
+        vec![b.bookmark.id, c.bookmark.id, a.bookmark.id]
+    );
+
+    proxy.delete(b.bookmark.id).unwrap();
+    assert_eq!(proxy.visits().unwrap().len(), 1);
+}
+
+fn tag_counts(proxy: &dyn BookmarkProxy) -> Vec<(String, i64)> {
+    proxy
+        .tags()
+        .unwrap()
+        .into_iter()
+        .map(|tag| (tag.tag, tag.count))
+        .collect()
+}
+
+fn tags_are_counted(proxy: &dyn BookmarkProxy) {
+    assert!(proxy.tags().unwrap().is_empty());
+
+    proxy
+        .add(&new_bookmark("https://one.com", "One", &["rust", "docs"]))
+        .unwrap();
+    let two = proxy
+        .add(&new_bookmark("https://two.com", "Two", &["rust", "old"]))
+        .unwrap()
+        .bookmark
+        .id;
+    proxy
+        .add(&new_bookmark("https://three.com", "Three", &[]))
+        .unwrap();
+
+    assert_eq!(
+        tag_counts(proxy),
+        vec![
+            ("docs".to_string(), 1),
+            ("old".to_string(), 1),
+            ("rust".to_string(), 2)
+        ]
+    );
+
+    proxy.delete(two).unwrap();
+    assert_eq!(
+        tag_counts(proxy),
+        vec![("docs".to_string(), 1), ("rust".to_string(), 1)]
+    );
+}
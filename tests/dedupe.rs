@@ -0,0 +1,82 @@
+// Copyright 2024 João Freitas
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of
+// the GNU General Public License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS FOR
+// A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+
+use marcador::bookmark::NewBookmark;
+use marcador::bookmark_proxy::BookmarkProxy;
+use marcador::local_proxy::LocalProxy;
+use marcador::normalise::{dedupe, default_tracking_params};
+
+#[test]
+fn dedupe_merges_near_duplicates_and_their_tags() {
+    let (_dir, db) = common::temp_db();
+    // Without tracking parameters these urls are all distinct.
+    let proxy = LocalProxy::with_tracking_params(&db, vec![]);
+
+    for (url, description, tags) in [
+        ("https://x.com/a", "", vec!["rust"]),
+        ("https://x.com/a?utm_source=feed", "A", vec!["cli", "rust"]),
+        ("https://x.com/b", "B", vec!["other"]),
+        ("http://x.com/a/?fbclid=1", "A again", vec!["web"]),
+    ] {
+        proxy
+            .add(&NewBookmark {
+                url: url.to_string(),
+                description: description.to_string(),
+                tags: tags.into_iter().map(|tag| tag.to_string()).collect(),
+                ..Default::default()
+            })
+            .unwrap();
+    }
+
+    let groups = dedupe(&proxy, &default_tracking_params()).unwrap();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].len(), 3);
+
+    let bookmarks = proxy.bookmarks().unwrap();
+    assert_eq!(bookmarks.len(), 2);
+
+    let kept = proxy.bookmark(1).unwrap();
+    assert_eq!(kept.bookmark.url, "https://x.com/a");
+    assert_eq!(kept.bookmark.description, "A");
+    let tags: Vec<&str> = kept.tags.iter().map(|tag| tag.tag.as_str()).collect();
+    assert_eq!(tags, vec!["rust", "cli", "web"]);
+
+    assert!(dedupe(&proxy, &default_tracking_params())
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn url_keys_follow_the_tracking_params() {
+    let (_dir, db) = common::temp_db();
+    let add = |proxy: &LocalProxy, url: &str| {
+        proxy.add(&NewBookmark {
+            url: url.to_string(),
+            ..Default::default()
+        })
+    };
+
+    add(
+        &LocalProxy::with_tracking_params(&db, vec![]),
+        "https://x.com/a",
+    )
+    .unwrap();
+
+    let proxy = LocalProxy::new(&db);
+    assert!(add(&proxy, "https://x.com/a?utm_source=feed").is_err());
+
+    let proxy = LocalProxy::with_tracking_params(&db, vec![]);
+    assert!(add(&proxy, "https://x.com/a?utm_source=feed").is_ok());
+}
@@ -0,0 +1,71 @@
+// Copyright 2024 João Freitas
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of
+// the GNU General Public License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS FOR
+// A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+mod common;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use marcador::bookmark::NewBookmark;
+use marcador::bookmark_proxy::BookmarkProxy;
+
+fn json<T: Serialize>(result: Result<T, String>) -> Result<Value, String> {
+    result.map(|value| serde_json::to_value(value).unwrap())
+}
+
+fn assert_parity<T: Serialize>(
+    local: &dyn BookmarkProxy,
+    remote: &dyn BookmarkProxy,
+    call: impl Fn(&dyn BookmarkProxy) -> Result<T, String>,
+) {
+    assert_eq!(json(call(local)), json(call(remote)));
+}
+
+#[test]
+fn local_and_remote_proxies_agree() {
+    let (_local_dir, local) = common::local_proxy();
+    let (_remote_dir, remote) = common::remote_proxy();
+
+    let rust = NewBookmark {
+        url: "https://www.rust-lang.org".to_string(),
+        description: "Rust".to_string(),
+        tags: vec!["rust".to_string(), "lang".to_string()],
+        ..Default::default()
+    };
+    let diesel = NewBookmark {
+        url: "https://diesel.rs".to_string(),
+        description: "Diesel".to_string(),
+        tags: vec!["rust".to_string(), "orm".to_string()],
+        ..Default::default()
+    };
+
+    assert_parity(&local, &remote, |proxy| proxy.add(&rust));
+    assert_parity(&local, &remote, |proxy| proxy.add(&diesel));
+    assert_parity(&local, &remote, |proxy| proxy.add(&rust));
+    assert_parity(&local, &remote, |proxy| proxy.bookmark(1));
+    assert_parity(&local, &remote, |proxy| proxy.bookmarks());
+
+    assert_parity(&local, &remote, |proxy| {
+        proxy.update_description(1, "The Rust language")
+    });
+    assert_parity(&local, &remote, |proxy| {
+        proxy.update_url(1, "https://rust-lang.org")
+    });
+    assert_parity(&local, &remote, |proxy| {
+        proxy.update_tags(2, &["orm".to_string(), "sql".to_string()])
+    });
+    assert_parity(&local, &remote, |proxy| proxy.bookmarks());
+
+    assert_parity(&local, &remote, |proxy| proxy.delete(1));
+    assert_parity(&local, &remote, |proxy| proxy.bookmarks());
+}
@@ -0,0 +1,159 @@
+// Copyright 2024 João Freitas
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of
+// the GNU General Public License as published by the Free Software Foundation, either
+// version 3 of the License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY
+// WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS FOR
+// A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Rofi script mode round trips, each request is one run of the script.
+
+mod common;
+
+use marcador::bookmark::NewBookmark;
+use marcador::bookmark_proxy::BookmarkProxy;
+use marcador::config::Config;
+use marcador::rofi_script::{respond, Menu, Request, View};
+
+fn request(retv: i32, selection: Option<&str>, info: Option<&str>, view: View) -> Request {
+    Request {
+        retv,
+        selection: selection.map(str::to_string),
+        info: info.map(str::to_string),
+        view,
+    }
+}
+
+fn run(proxy: &dyn BookmarkProxy, request: Request) -> Menu {
+    let config = Config {
+        fetch_metadata: Some(false),
+        ..Default::default()
+    };
+    respond(proxy, &config, &request).unwrap().unwrap()
+}
+
+fn tags(proxy: &dyn BookmarkProxy, id: i32) -> Vec<String> {
+    proxy
+        .bookmark(id)
+        .unwrap()
+        .tags
+        .into_iter()
+        .map(|tag| tag.tag)
+        .collect()
+}
+
+#[test]
+fn script_mode_lists_adds_tags_and_deletes() {
+    let (_dir, proxy) = common::local_proxy();
+    let id = proxy
+        .add(&NewBookmark {
+            url: "https://www.rust-lang.org".to_string(),
+            description: "Rust".to_string(),
+            tags: vec!["lang".to_string()],
+            ..Default::default()
+        })
+        .unwrap()
+        .bookmark
+        .id;
+    let info = id.to_string();
+
+    let menu = run(&proxy, request(0, None, None, View::Bookmarks));
+    assert_eq!(menu.view, View::Bookmarks);
+    assert!(!menu.message.as_ref().unwrap().contains("Edit"));
+    assert_eq!(menu.rows.len(), 1);
+    assert_eq!(menu.rows[0].info, info);
+    let output = menu.to_string();
+    assert!(output.starts_with("\0prompt\x1fBookmarks\n"));
+    assert!(output.contains("\0data\x1fbookmarks\n"));
+    assert!(output.ends_with(&format!(
+        "\0info\x1f{}\x1fmeta\x1fhttps://www.rust-lang.org\n",
+        id
+    )));
+
+    let menu = run(
+        &proxy,
+        request(2, Some("not an url"), None, View::Bookmarks),
+    );
+    assert!(menu.message.unwrap().contains("Not an url"));
+    let menu = run(
+        &proxy,
+        request(2, Some("https://docs.rs"), None, View::Bookmarks),
+    );
+    assert_eq!(menu.rows.len(), 2);
+
+    // Alt+e, the third default keybinding, needs a terminal
+    let menu = run(&proxy, request(12, None, Some(&info), View::Bookmarks));
+    assert!(menu
+        .message
+        .unwrap()
+        .contains("Edit is not available in script mode"));
+
+    // Alt+t, the fourth default keybinding, adds tags
+    let menu = run(&proxy, request(13, None, Some(&info), View::Bookmarks));
+    assert_eq!(menu.view, View::AddTags(id));
+    assert!(menu.custom);
+    run(
+        &proxy,
+        request(2, Some("docs web"), None, View::AddTags(id)),
+    );
+    assert_eq!(tags(&proxy, id), vec!["lang", "docs", "web"]);
+
+    let menu = run(&proxy, request(14, None, Some(&info), View::Bookmarks));
+    assert_eq!(menu.view, View::RemoveTags(id));
+    assert_eq!(menu.rows.len(), 3);
+    run(
+        &proxy,
+        request(1, Some("#web"), Some("web"), View::RemoveTags(id)),
+    );
+    assert_eq!(tags(&proxy, id), vec!["lang", "docs"]);
+
+    let menu = run(&proxy, request(11, None, Some(&info), View::Bookmarks));
+    assert_eq!(menu.view, View::ConfirmDelete(id));
+    assert!(!menu.custom);
+    run(
+        &proxy,
+        request(1, Some("No"), Some("no"), View::ConfirmDelete(id)),
+    );
+    assert!(proxy.bookmark(id).is_ok());
+    run(
+        &proxy,
+        request(1, Some("Yes"), Some("yes"), View::ConfirmDelete(id)),
+    );
+    assert!(proxy.bookmark(id).is_err());
+
+    let menu = run(&proxy, request(28, None, None, View::Bookmarks));
+    assert!(menu
+        .message
+        .unwrap()
+        .contains("No action for keybinding 19"));
+}
+
+#[test]
+fn views_survive_rofi_data() {
+    for view in [
+        View::Bookmarks,
+        View::ConfirmDelete(3),
+        View::AddTags(4),
+        View::RemoveTags(5),
+    ] {
+        let menu = Menu {
+            view: view.clone(),
+            prompt: String::new(),
+            message: None,
+            custom: false,
+            rows: vec![],
+        };
+        let output = menu.to_string();
+        let data = output
+            .lines()
+            .find_map(|line| line.strip_prefix("\0data\x1f"))
+            .unwrap();
+        assert_eq!(View::parse(data), view);
+    }
+    assert_eq!(View::parse("delete:oops"), View::Bookmarks);
+}